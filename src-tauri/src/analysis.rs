@@ -0,0 +1,260 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+
+use crate::diagnostics::{self, Diagnostic, DiagnosticFormat};
+use crate::execution::AppState;
+use crate::process;
+use crate::toolchains;
+use crate::workspace::BuildWorkspaces;
+
+/// Each linter is killed past this.
+const LINT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// ESLint only lints with the project's rules when it has a flat config.
+const ESLINT_CONFIGS: [&str; 6] = [
+    "eslint.config.js",
+    "eslint.config.mjs",
+    "eslint.config.cjs",
+    "eslint.config.ts",
+    "eslint.config.mts",
+    "eslint.config.cts",
+];
+
+/// Used for JavaScript in projects without an ESLint config: the
+/// recommended rules that catch bugs rather than style.
+const ESLINT_DEFAULT_RULES: [&str; 12] = [
+    "no-unused-vars: warn",
+    "no-shadow: warn",
+    "no-unreachable: warn",
+    "no-dupe-keys: error",
+    "no-self-assign: warn",
+    "no-self-compare: warn",
+    "no-constant-condition: warn",
+    "no-cond-assign: warn",
+    "no-fallthrough: warn",
+    "no-redeclare: error",
+    "use-isnan: error",
+    "valid-typeof: error",
+];
+
+#[derive(Serialize)]
+pub struct Analysis {
+    /// Every linter's diagnostics, ordered by line. `source` names the
+    /// linter and `code` the rule.
+    pub diagnostics: Vec<Diagnostic>,
+    /// Linters that ran.
+    pub linters: Vec<String>,
+    /// Linters for the language that aren't installed.
+    pub missing: Vec<String>,
+    /// Linters that ran but couldn't lint, with their error.
+    pub failures: Vec<String>,
+}
+
+struct Linter {
+    name: &'static str,
+    argv: Vec<String>,
+    format: DiagnosticFormat,
+    /// Where it runs. Diagnostics are made relative to it.
+    dir: PathBuf,
+    /// The code goes to stdin rather than being read from the file.
+    stdin: bool,
+}
+
+fn args(argv: &[&str]) -> Vec<String> {
+    argv.iter().map(|arg| arg.to_string()).collect()
+}
+
+/// The linters that apply to a toolchain, installed or not.
+fn linters(toolchain: &str, source: &Path, scratch: &Path, cwd: &Path) -> Vec<Linter> {
+    let file = source.to_string_lossy().into_owned();
+    let name = source
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let linter = |name, mut argv: Vec<String>, format| {
+        argv.push(file.clone());
+        Linter {
+            name,
+            argv,
+            format,
+            dir: scratch.to_path_buf(),
+            stdin: false,
+        }
+    };
+    let warnings = ["-fsyntax-only", "-Wall", "-Wextra", "-Wshadow"];
+    let cppcheck = || {
+        linter(
+            "cppcheck",
+            args(&[
+                "cppcheck",
+                "--enable=warning,style,performance,portability",
+                "--quiet",
+                "--template={file}:{line}:{column}: {severity}: {message} [{id}]",
+            ]),
+            DiagnosticFormat::Cppcheck,
+        )
+    };
+
+    match toolchain {
+        "rust" => vec![linter(
+            "clippy",
+            args(&[
+                "clippy-driver",
+                "--edition=2021",
+                "--crate-type=bin",
+                "--error-format=json",
+                "--emit=metadata",
+                "--out-dir",
+                &scratch.to_string_lossy(),
+                "-W",
+                "clippy::all",
+            ]),
+            DiagnosticFormat::Rustc,
+        )],
+        "c" => vec![
+            linter(
+                "gcc",
+                args(&[&["gcc"][..], &warnings].concat()),
+                DiagnosticFormat::Gcc,
+            ),
+            cppcheck(),
+        ],
+        "c++" => vec![
+            linter(
+                "g++",
+                args(&[&["g++"][..], &warnings].concat()),
+                DiagnosticFormat::Gcc,
+            ),
+            cppcheck(),
+        ],
+        "python" => vec![linter(
+            "pyflakes",
+            args(&["pyflakes"]),
+            DiagnosticFormat::Pyflakes,
+        )],
+        "go" => vec![linter(
+            "go vet",
+            args(&["go", "vet", "-json"]),
+            DiagnosticFormat::GoVet,
+        )],
+        "javascript" | "typescript" => {
+            let has_config = ESLINT_CONFIGS.iter().any(|c| cwd.join(c).is_file());
+            // Without a config there's nothing to parse TypeScript with
+            if !has_config && toolchain == "typescript" {
+                return Vec::new();
+            }
+
+            // Linted from the project directory so its config applies
+            let mut argv = args(&["eslint", "--format", "json", "--stdin"]);
+            argv.extend(["--stdin-filename".to_string(), name]);
+            if !has_config {
+                argv.push("--no-config-lookup".to_string());
+                for rule in ESLINT_DEFAULT_RULES {
+                    argv.extend(["--rule".to_string(), rule.to_string()]);
+                }
+            }
+            vec![Linter {
+                name: "eslint",
+                argv,
+                format: DiagnosticFormat::Eslint,
+                dir: cwd.to_path_buf(),
+                stdin: true,
+            }]
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Runs the installed linters for the language over the code, without
+/// building or running it, and merges their findings.
+#[tauri::command]
+pub async fn analyze_code(
+    app: AppHandle,
+    language: String,
+    code: String,
+    state: State<'_, AppState>,
+) -> Result<Analysis, String> {
+    let cwd = state.cwd.lock().map_err(|e| e.to_string())?.clone();
+    let toolchains = toolchains::load(&app, &cwd)?;
+    let toolchain = toolchains
+        .find(&language)
+        .ok_or_else(|| format!("Unsupported language for analysis: {}", language))?;
+
+    let scratch = app.state::<BuildWorkspaces>().create_scratch("analysis")?;
+    let source = scratch.join(format!("main.{}", toolchain.extension));
+    let result = match std::fs::write(&source, &code) {
+        Ok(()) => Ok(run_linters(&toolchain.name, &code, &source, &scratch, &cwd).await),
+        Err(e) => Err(e.to_string()),
+    };
+    let _ = std::fs::remove_dir_all(&scratch);
+    result
+}
+
+async fn run_linters(
+    toolchain: &str,
+    code: &str,
+    source: &Path,
+    scratch: &Path,
+    cwd: &Path,
+) -> Analysis {
+    let mut analysis = Analysis {
+        diagnostics: Vec::new(),
+        linters: Vec::new(),
+        missing: Vec::new(),
+        failures: Vec::new(),
+    };
+
+    for linter in linters(toolchain, source, scratch, cwd) {
+        let Some(program) = toolchains::find_program(&linter.argv[0]) else {
+            analysis.missing.push(linter.name.to_string());
+            continue;
+        };
+        analysis.linters.push(linter.name.to_string());
+
+        let mut cmd = tokio::process::Command::new(&program);
+        cmd.args(&linter.argv[1..]).current_dir(&linter.dir);
+        let input = linter.stdin.then_some(code.as_bytes());
+        let output = match process::output(cmd, input, LINT_TIMEOUT).await {
+            Ok(output) => output,
+            Err(e) => {
+                analysis.failures.push(format!("{}: {}", linter.name, e));
+                continue;
+            }
+        };
+        let text = if linter.format.uses_stdout() {
+            &output.stdout
+        } else {
+            &output.stderr
+        };
+        let parsed = diagnostics::parse(linter.format, &String::from_utf8_lossy(text), &linter.dir);
+
+        // Linters exit non-zero when they find something, so a failure is
+        // an error exit with nothing parsed
+        if parsed.diagnostics.is_empty() && !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let reason = stderr
+                .lines()
+                .find(|l| !l.trim().is_empty())
+                .unwrap_or("failed");
+            analysis
+                .failures
+                .push(format!("{}: {}", linter.name, reason.trim()));
+            continue;
+        }
+
+        analysis
+            .diagnostics
+            .extend(parsed.diagnostics.into_iter().map(|mut diagnostic| {
+                diagnostic.source = Some(linter.name.to_string());
+                diagnostic
+            }));
+    }
+
+    // Stable, so each line keeps the linters' own order
+    analysis
+        .diagnostics
+        .sort_by_key(|d| (d.line.is_none(), d.line, d.column));
+    analysis
+}
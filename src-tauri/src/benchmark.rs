@@ -0,0 +1,314 @@
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::execution::{AppState, ExecutionResult, PreparedRun, RunRegistry, RunStatus};
+use crate::run_config::RunConfig;
+
+const MAX_ITERATIONS: u32 = 1000;
+
+/// Largest input size when scaling. `{numbers}` makes about 10 bytes of
+/// input per unit, so this keeps inputs around 10 MB.
+const MAX_SCALING_SIZE: u64 = 1_000_000;
+
+const MAX_SCALING_SIZES: usize = 64;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct BenchmarkOptions {
+    /// Unmeasured runs first, to warm caches.
+    pub warmup: u32,
+    pub iterations: u32,
+    /// Stdin for every run. Without it stdin is empty.
+    pub input: Option<String>,
+    /// Runs the benchmark at several input sizes to estimate how the run
+    /// time grows. Takes the place of `input`.
+    pub scaling: Option<InputScaling>,
+}
+
+impl Default for BenchmarkOptions {
+    fn default() -> Self {
+        Self {
+            warmup: 1,
+            iterations: 5,
+            input: None,
+            scaling: None,
+        }
+    }
+}
+
+/// Input generated for each size `n`: `{n}` in the template is replaced by
+/// `n`, and `{numbers}` by `n` space-separated pseudo-random integers (the
+/// same ones on every run).
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct InputScaling {
+    pub sizes: Vec<u64>,
+    pub template: String,
+}
+
+/// Wall-clock statistics over the measured runs, in milliseconds.
+#[derive(Serialize, Clone)]
+pub struct TimingStats {
+    pub runs: usize,
+    pub min_ms: f64,
+    pub median_ms: f64,
+    pub mean_ms: f64,
+    pub stddev_ms: f64,
+    /// Highest peak RSS of any measured run, in KiB (Unix only).
+    pub peak_memory_kb: Option<u64>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct BenchmarkPoint {
+    /// Input size, when scaling.
+    pub size: Option<u64>,
+    pub stats: TimingStats,
+}
+
+/// Power-law fit of median run time against input size.
+#[derive(Serialize, Clone)]
+pub struct GrowthEstimate {
+    /// `k` in time ~ n^k.
+    pub exponent: f64,
+    /// The nearest common complexity class, e.g. "O(n^2)".
+    pub complexity: String,
+}
+
+#[derive(Serialize, Clone)]
+pub struct BenchmarkReport {
+    pub run_id: u64,
+    /// The compile phase's result when the build failed; nothing ran then.
+    pub build_error: Option<ExecutionResult>,
+    pub compile_time_ms: Option<u64>,
+    pub points: Vec<BenchmarkPoint>,
+    pub growth: Option<GrowthEstimate>,
+}
+
+/// Sent as `benchmark-progress` after every run, warmups included.
+#[derive(Serialize, Clone)]
+struct BenchmarkProgress {
+    run_id: u64,
+    completed: u64,
+    total: u64,
+}
+
+/// Builds the code once, then times repeated runs of it. Output is captured,
+/// not streamed. A run that fails or is stopped with `kill_execution` ends
+/// the benchmark with an error.
+#[tauri::command]
+pub async fn benchmark_code(
+    app: AppHandle,
+    language: String,
+    code: String,
+    options: Option<BenchmarkOptions>,
+    state: State<'_, AppState>,
+    registry: State<'_, RunRegistry>,
+) -> Result<BenchmarkReport, String> {
+    let options = options.unwrap_or_default();
+    if options.iterations == 0 || options.iterations > MAX_ITERATIONS {
+        return Err(format!(
+            "Iterations must be between 1 and {}",
+            MAX_ITERATIONS
+        ));
+    }
+    if options.warmup > MAX_ITERATIONS {
+        return Err(format!("At most {} warmup runs", MAX_ITERATIONS));
+    }
+    if let Some(scaling) = &options.scaling {
+        if scaling.sizes.is_empty() {
+            return Err("Input scaling needs at least one size".to_string());
+        }
+        if scaling.sizes.len() > MAX_SCALING_SIZES {
+            return Err(format!(
+                "Input scaling takes at most {} sizes",
+                MAX_SCALING_SIZES
+            ));
+        }
+        if scaling.sizes.iter().any(|&n| n > MAX_SCALING_SIZE) {
+            return Err(format!("Input sizes must be at most {}", MAX_SCALING_SIZE));
+        }
+    }
+
+    let cwd = state.cwd.lock().map_err(|e| e.to_string())?.clone();
+    let prepared = PreparedRun::new(&app, &cwd, &language, &code, RunConfig::default())?;
+    let run_id = prepared.run_id;
+    registry.insert(run_id, prepared.initial_status());
+
+    let result = run_benchmark(&app, &prepared, &options).await;
+
+    registry.remove(run_id);
+    prepared.release(&app);
+    result
+}
+
+async fn run_benchmark(
+    app: &AppHandle,
+    prepared: &PreparedRun,
+    options: &BenchmarkOptions,
+) -> Result<BenchmarkReport, String> {
+    let run_id = prepared.run_id;
+    let mut report = BenchmarkReport {
+        run_id,
+        build_error: None,
+        compile_time_ms: None,
+        points: Vec::new(),
+        growth: None,
+    };
+
+    match prepared.build(app).await {
+        Ok(compiled) => report.compile_time_ms = compiled.and_then(|c| c.compile_time_ms),
+        Err(failed) => {
+            report.build_error = Some(failed);
+            return Ok(report);
+        }
+    }
+    app.state::<RunRegistry>()
+        .set_status(run_id, RunStatus::Running);
+
+    // Each size's input is generated when its turn comes, so only one is
+    // held at a time
+    let sizes: Vec<Option<u64>> = match &options.scaling {
+        Some(scaling) => scaling.sizes.iter().copied().map(Some).collect(),
+        None => vec![None],
+    };
+
+    let total = u64::from(options.warmup + options.iterations) * sizes.len() as u64;
+    let mut completed = 0;
+    for size in sizes {
+        let input = match (&options.scaling, size) {
+            (Some(scaling), Some(n)) => generate_input(&scaling.template, n),
+            _ => options.input.clone().unwrap_or_default(),
+        };
+        let mut times = Vec::new();
+        let mut peak_memory_kb: Option<u64> = None;
+
+        for iteration in 0..options.warmup + options.iterations {
+            let result = prepared.run_with_input(app, input.as_bytes()).await;
+            if !result.success {
+                return Err(describe_failure(&result));
+            }
+
+            completed += 1;
+            let _ = app.emit(
+                "benchmark-progress",
+                BenchmarkProgress {
+                    run_id,
+                    completed,
+                    total,
+                },
+            );
+
+            if iteration >= options.warmup {
+                times.push(result.run_elapsed.unwrap_or_default().as_secs_f64() * 1000.0);
+                peak_memory_kb = peak_memory_kb.max(result.peak_memory_kb);
+            }
+        }
+
+        report.points.push(BenchmarkPoint {
+            size,
+            stats: statistics(times, peak_memory_kb),
+        });
+    }
+
+    if options.scaling.is_some() {
+        report.growth = estimate_growth(&report.points);
+    }
+    Ok(report)
+}
+
+fn describe_failure(result: &ExecutionResult) -> String {
+    if let Some(error) = &result.error {
+        return error.clone();
+    }
+    if let Some(reason) = result.reason {
+        return format!("Benchmark run {}", reason.describe());
+    }
+    match result.exit_code {
+        Some(code) => format!("Benchmark run exited with code {}", code),
+        None => "Benchmark run was terminated by a signal".to_string(),
+    }
+}
+
+fn statistics(mut times: Vec<f64>, peak_memory_kb: Option<u64>) -> TimingStats {
+    times.sort_by(|a, b| a.total_cmp(b));
+    let runs = times.len();
+    let mean = times.iter().sum::<f64>() / runs as f64;
+    let median = if runs % 2 == 0 {
+        (times[runs / 2 - 1] + times[runs / 2]) / 2.0
+    } else {
+        times[runs / 2]
+    };
+    // Sample standard deviation; a single run has none
+    let stddev = if runs > 1 {
+        (times.iter().map(|t| (t - mean).powi(2)).sum::<f64>() / (runs - 1) as f64).sqrt()
+    } else {
+        0.0
+    };
+
+    TimingStats {
+        runs,
+        min_ms: times[0],
+        median_ms: median,
+        mean_ms: mean,
+        stddev_ms: stddev,
+        peak_memory_kb,
+    }
+}
+
+/// Least-squares slope of log(median time) against log(size).
+fn estimate_growth(points: &[BenchmarkPoint]) -> Option<GrowthEstimate> {
+    let samples: Vec<(f64, f64)> = points
+        .iter()
+        .filter_map(|point| {
+            let size = point.size? as f64;
+            let time = point.stats.median_ms;
+            (size > 0.0 && time > 0.0).then(|| (size.ln(), time.ln()))
+        })
+        .collect();
+    if samples.len() < 2 {
+        return None;
+    }
+
+    let count = samples.len() as f64;
+    let mean_x = samples.iter().map(|(x, _)| x).sum::<f64>() / count;
+    let mean_y = samples.iter().map(|(_, y)| y).sum::<f64>() / count;
+    let covariance: f64 = samples
+        .iter()
+        .map(|(x, y)| (x - mean_x) * (y - mean_y))
+        .sum();
+    let variance: f64 = samples.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+    if variance == 0.0 {
+        return None;
+    }
+
+    let exponent = covariance / variance;
+    let complexity = match exponent {
+        k if k < 0.5 => "O(1)",
+        k if k < 1.5 => "O(n)",
+        k if k < 2.5 => "O(n^2)",
+        k if k < 3.5 => "O(n^3)",
+        _ => "worse than O(n^3)",
+    };
+
+    Some(GrowthEstimate {
+        exponent,
+        complexity: complexity.to_string(),
+    })
+}
+
+fn generate_input(template: &str, n: u64) -> String {
+    let mut input = template.replace("{n}", &n.to_string());
+    if input.contains("{numbers}") {
+        // xorshift, seeded so every run sees the same input
+        let mut state: u64 = 0x9E37_79B9_7F4A_7C15;
+        let numbers: Vec<String> = (0..n)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                (state % 1_000_000_000).to_string()
+            })
+            .collect();
+        input = input.replace("{numbers}", &numbers.join(" "));
+    }
+    input
+}
@@ -0,0 +1,335 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::State;
+
+use crate::diagnostics::Diagnostic;
+use crate::process;
+use crate::toolchains::Toolchain;
+
+const ENTRY_FILE: &str = "entry.json";
+
+/// A compiler's version output, with the modification time of the compiler
+/// when it was asked, so an upgrade is noticed.
+type KnownVersion = (Option<SystemTime>, String);
+
+static NEXT_STAGING_ID: AtomicU64 = AtomicU64::new(1);
+
+/// A compiler that takes longer than this to print its version isn't cached
+/// for.
+const VERSION_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Least recently used entries are evicted past this total size.
+const MAX_CACHE_BYTES: u64 = 512 * 1024 * 1024;
+
+/// Compiled single-file programs, under the app cache dir, addressed by a
+/// hash of everything the build depends on: the code, the toolchain and its
+/// compile command, and the compiler's version. Unlike build directories the
+/// cache outlives the session.
+///
+/// Each entry is a directory named after its key, holding the binary and an
+/// `entry.json` with the diagnostics of the build that produced it.
+pub struct CompileCache {
+    root: PathBuf,
+    /// Per version command.
+    versions: Mutex<HashMap<Vec<String>, KnownVersion>>,
+}
+
+/// A single-file build whose output can be cached.
+pub struct CacheSlot {
+    language: String,
+    extension: String,
+    template: Vec<String>,
+    version: Vec<String>,
+    code: String,
+    /// Where the build writes the binary, and where a cached one is restored.
+    pub binary: PathBuf,
+}
+
+impl CacheSlot {
+    /// `None` unless the toolchain compiles to `{binary}`: interpreted
+    /// languages and type-check-only steps have nothing to cache.
+    /// `extra_flags` are appended to the compile command for this build.
+    pub fn new(
+        toolchain: &Toolchain,
+        extra_flags: &[String],
+        code: &str,
+        binary: &Path,
+    ) -> Option<Self> {
+        let template = toolchain.compile.as_ref()?;
+        if !template.iter().any(|arg| arg.contains("{binary}")) {
+            return None;
+        }
+
+        Some(Self {
+            language: toolchain.name.clone(),
+            extension: toolchain.extension.clone(),
+            template: template.iter().chain(extra_flags).cloned().collect(),
+            version: toolchain.version_argv()?,
+            code: code.to_string(),
+            binary: binary.to_path_buf(),
+        })
+    }
+}
+
+pub struct CacheKey {
+    digest: String,
+    compiler_version: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct EntryMeta {
+    language: String,
+    compiler_version: String,
+    /// Unix timestamps, in seconds.
+    created: u64,
+    last_used: u64,
+    diagnostics: Vec<Diagnostic>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct CacheEntryInfo {
+    pub key: String,
+    pub language: String,
+    pub compiler_version: String,
+    pub size_bytes: u64,
+    pub created: u64,
+    pub last_used: u64,
+}
+
+#[derive(Serialize, Clone)]
+pub struct CompileCacheInfo {
+    pub path: String,
+    /// Most recently used first.
+    pub entries: Vec<CacheEntryInfo>,
+    pub total_bytes: u64,
+}
+
+impl CompileCache {
+    /// Staging directories left behind by an interrupted store are removed.
+    pub fn new(root: PathBuf) -> Self {
+        for entry in std::fs::read_dir(&root).into_iter().flatten().flatten() {
+            if entry.file_name().to_string_lossy().starts_with(".staging-") {
+                let _ = std::fs::remove_dir_all(entry.path());
+            }
+        }
+        Self {
+            root,
+            versions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The slot's cache key. `None` when the compiler's version can't be
+    /// determined, in which case the build isn't cached.
+    pub async fn key(&self, slot: &CacheSlot) -> Option<CacheKey> {
+        let compiler_version = self.compiler_version(&slot.version).await?;
+
+        let mut hasher = Sha256::new();
+        let parts = [&slot.language, &slot.extension, &compiler_version]
+            .into_iter()
+            .chain(&slot.template)
+            .chain(std::iter::once(&slot.code));
+        for part in parts {
+            // Length-prefixed so neighbouring parts can't run into each other
+            hasher.update((part.len() as u64).to_le_bytes());
+            hasher.update(part.as_bytes());
+        }
+        let digest = hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+
+        Some(CacheKey {
+            digest,
+            compiler_version,
+        })
+    }
+
+    async fn compiler_version(&self, argv: &[String]) -> Option<String> {
+        let modified = std::fs::metadata(&argv[0])
+            .and_then(|meta| meta.modified())
+            .ok();
+        if let Ok(versions) = self.versions.lock() {
+            if let Some((when, version)) = versions.get(argv) {
+                if *when == modified {
+                    return Some(version.clone());
+                }
+            }
+        }
+
+        let mut cmd = tokio::process::Command::new(&argv[0]);
+        cmd.args(&argv[1..]);
+        let output = process::output(cmd, None, VERSION_TIMEOUT).await.ok()?;
+        if !output.status.success() {
+            return None;
+        }
+
+        // Some compilers print their version to stderr
+        let version = format!(
+            "{}{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        )
+        .trim()
+        .to_string();
+        if let Ok(mut versions) = self.versions.lock() {
+            versions.insert(argv.to_vec(), (modified, version.clone()));
+        }
+        Some(version)
+    }
+
+    /// Copies the cached binary for `key` to `binary`. Returns the
+    /// diagnostics of the original build on a hit.
+    pub fn restore(&self, key: &CacheKey, binary: &Path) -> Option<Vec<Diagnostic>> {
+        let dir = self.root.join(&key.digest);
+        let mut meta = read_meta(&dir)?;
+        std::fs::copy(dir.join(program_name()), binary).ok()?;
+
+        meta.last_used = now();
+        let _ = write_meta(&dir, &meta);
+        Some(meta.diagnostics)
+    }
+
+    /// Adds a successful build's binary under `key`, then evicts old entries
+    /// if the cache has grown too big.
+    pub fn store(
+        &self,
+        key: &CacheKey,
+        slot: &CacheSlot,
+        diagnostics: &[Diagnostic],
+    ) -> Result<(), String> {
+        let dir = self.root.join(&key.digest);
+        if dir.exists() {
+            return Ok(());
+        }
+
+        // Filled in beside the entry and renamed into place, so a concurrent
+        // restore never sees half an entry
+        let staging = self.root.join(format!(
+            ".staging-{}-{}",
+            key.digest,
+            NEXT_STAGING_ID.fetch_add(1, Ordering::Relaxed)
+        ));
+        let result = (|| {
+            std::fs::create_dir_all(&staging).map_err(|e| e.to_string())?;
+            std::fs::copy(&slot.binary, staging.join(program_name())).map_err(|e| e.to_string())?;
+            let created = now();
+            write_meta(
+                &staging,
+                &EntryMeta {
+                    language: slot.language.clone(),
+                    compiler_version: key.compiler_version.clone(),
+                    created,
+                    last_used: created,
+                    diagnostics: diagnostics.to_vec(),
+                },
+            )?;
+            std::fs::rename(&staging, &dir).map_err(|e| e.to_string())
+        })();
+        if result.is_err() {
+            let _ = std::fs::remove_dir_all(&staging);
+        }
+        result.map_err(|e| format!("Failed to cache build: {}", e))?;
+
+        self.evict();
+        Ok(())
+    }
+
+    fn evict(&self) {
+        let mut info = self.info();
+        while info.total_bytes > MAX_CACHE_BYTES {
+            let Some(oldest) = info.entries.pop() else {
+                break;
+            };
+            let _ = std::fs::remove_dir_all(self.root.join(&oldest.key));
+            info.total_bytes -= oldest.size_bytes;
+        }
+    }
+
+    pub fn info(&self) -> CompileCacheInfo {
+        let mut entries: Vec<CacheEntryInfo> = std::fs::read_dir(&self.root)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+            .filter_map(|entry| {
+                let dir = entry.path();
+                let meta = read_meta(&dir)?;
+                Some(CacheEntryInfo {
+                    key: entry.file_name().to_string_lossy().into_owned(),
+                    language: meta.language,
+                    compiler_version: meta.compiler_version,
+                    size_bytes: dir_size(&dir),
+                    created: meta.created,
+                    last_used: meta.last_used,
+                })
+            })
+            .collect();
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.last_used));
+
+        CompileCacheInfo {
+            path: self.root.to_string_lossy().into_owned(),
+            total_bytes: entries.iter().map(|entry| entry.size_bytes).sum(),
+            entries,
+        }
+    }
+
+    pub fn clear(&self) -> Result<(), String> {
+        match std::fs::remove_dir_all(&self.root) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(format!("Failed to clear the compile cache: {}", e))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+fn program_name() -> &'static str {
+    if cfg!(target_os = "windows") {
+        "program.exe"
+    } else {
+        "program"
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn read_meta(dir: &Path) -> Option<EntryMeta> {
+    let content = std::fs::read_to_string(dir.join(ENTRY_FILE)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+fn write_meta(dir: &Path, meta: &EntryMeta) -> Result<(), String> {
+    let content = serde_json::to_string(meta).map_err(|e| e.to_string())?;
+    std::fs::write(dir.join(ENTRY_FILE), content).map_err(|e| e.to_string())
+}
+
+fn dir_size(dir: &Path) -> u64 {
+    std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| entry.metadata().ok())
+        .map(|meta| meta.len())
+        .sum()
+}
+
+#[tauri::command]
+pub fn get_compile_cache_info(cache: State<'_, CompileCache>) -> CompileCacheInfo {
+    cache.info()
+}
+
+#[tauri::command]
+pub fn clear_compile_cache(cache: State<'_, CompileCache>) -> Result<(), String> {
+    cache.clear()
+}
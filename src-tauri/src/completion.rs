@@ -0,0 +1,297 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+use tauri::State;
+
+use crate::execution::AppState;
+use crate::process;
+use crate::shell;
+
+const MAX_CANDIDATES: usize = 200;
+
+/// git is killed past this, and the built-in list used instead.
+const GIT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Used when git itself can't list its commands.
+const GIT_SUBCOMMANDS: [&str; 22] = [
+    "add",
+    "bisect",
+    "blame",
+    "branch",
+    "checkout",
+    "cherry-pick",
+    "clone",
+    "commit",
+    "diff",
+    "fetch",
+    "init",
+    "log",
+    "merge",
+    "mv",
+    "pull",
+    "push",
+    "rebase",
+    "reset",
+    "restore",
+    "rm",
+    "stash",
+    "status",
+];
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CompletionKind {
+    File,
+    Directory,
+    Executable,
+    Builtin,
+    GitSubcommand,
+}
+
+#[derive(Serialize, Clone)]
+pub struct Completion {
+    /// Replaces `Completions::word`, quoted or escaped as needed.
+    pub value: String,
+    pub kind: CompletionKind,
+}
+
+#[derive(Serialize, Clone)]
+pub struct Completions {
+    /// The end of the input being completed, exactly as typed.
+    pub word: String,
+    /// Sorted by value.
+    pub candidates: Vec<Completion>,
+}
+
+/// The command being typed at the end of the input.
+struct Cursor {
+    /// Earlier words of the same command, unquoted.
+    words: Vec<String>,
+    /// The word being completed as typed, and unquoted.
+    raw: String,
+    value: String,
+    /// The quote the word is still inside, if any.
+    open_quote: Option<char>,
+}
+
+fn parse_cursor(input: &str) -> Cursor {
+    let mut cursor = Cursor {
+        words: Vec::new(),
+        raw: String::new(),
+        value: String::new(),
+        open_quote: None,
+    };
+    let mut started = false;
+    let mut chars = input.chars();
+
+    while let Some(c) = chars.next() {
+        match (cursor.open_quote, c) {
+            (Some(q), c) if c == q => {
+                cursor.open_quote = None;
+                cursor.raw.push(c);
+            }
+            (Some(_), c) => {
+                cursor.raw.push(c);
+                cursor.value.push(c);
+            }
+            (None, '\'' | '"') => {
+                cursor.open_quote = Some(c);
+                cursor.raw.push(c);
+                started = true;
+            }
+            (None, '\\') => {
+                cursor.raw.push(c);
+                if let Some(escaped) = chars.next() {
+                    cursor.raw.push(escaped);
+                    cursor.value.push(escaped);
+                }
+                started = true;
+            }
+            (None, c) if c.is_whitespace() || matches!(c, ';' | '|' | '&' | '(') => {
+                if started {
+                    cursor.words.push(std::mem::take(&mut cursor.value));
+                    started = false;
+                }
+                cursor.raw.clear();
+                // A separator starts the next command
+                if !c.is_whitespace() {
+                    cursor.words.clear();
+                }
+            }
+            (None, c) => {
+                cursor.raw.push(c);
+                cursor.value.push(c);
+                started = true;
+            }
+        }
+    }
+
+    cursor
+}
+
+/// Completes the last word of `input`: git subcommands after `git`,
+/// builtins and programs on PATH in command position, and otherwise paths
+/// relative to the terminal's working directory.
+#[tauri::command]
+pub async fn complete_terminal_input(
+    input: String,
+    state: State<'_, AppState>,
+) -> Result<Completions, String> {
+    let cwd = state.cwd.lock().map_err(|e| e.to_string())?.clone();
+    let cursor = parse_cursor(&input);
+
+    let mut found: BTreeMap<String, CompletionKind> = BTreeMap::new();
+    let is_git_subcommand = cursor.words.len() == 1 && cursor.words[0] == "git";
+    let is_command = cursor.words.is_empty() && !cursor.value.contains('/');
+
+    if is_git_subcommand && !cursor.value.starts_with('-') {
+        for subcommand in git_subcommands().await {
+            if subcommand.starts_with(&cursor.value) {
+                found.insert(subcommand, CompletionKind::GitSubcommand);
+            }
+        }
+    } else if is_command {
+        for builtin in shell::BUILTINS {
+            if builtin.starts_with(&cursor.value) {
+                found.insert(builtin.to_string(), CompletionKind::Builtin);
+            }
+        }
+        for program in executables(&cursor.value) {
+            found.entry(program).or_insert(CompletionKind::Executable);
+        }
+    } else {
+        found = paths(&cwd, &cursor.value);
+    }
+
+    let candidates = found
+        .into_iter()
+        .take(MAX_CANDIDATES)
+        .map(|(value, kind)| Completion {
+            value: quote(&value, cursor.open_quote),
+            kind,
+        })
+        .collect();
+
+    Ok(Completions {
+        word: cursor.raw,
+        candidates,
+    })
+}
+
+/// Writes a candidate the way the word was started: inside the open quote,
+/// or with special characters escaped. The quote is left open so the user
+/// can keep typing.
+fn quote(value: &str, open_quote: Option<char>) -> String {
+    match open_quote {
+        Some(q) => format!("{}{}", q, value),
+        None => {
+            let mut escaped = String::new();
+            for c in value.chars() {
+                if c.is_whitespace() || "\\'\"$`&|;()<>*?!#".contains(c) {
+                    escaped.push('\\');
+                }
+                escaped.push(c);
+            }
+            escaped
+        }
+    }
+}
+
+/// Entries matching `partial`, a path typed so far. Directories end in
+/// `/`; hidden entries only show up once a `.` has been typed.
+fn paths(cwd: &Path, partial: &str) -> BTreeMap<String, CompletionKind> {
+    let (dir_part, name_part) = match partial.rfind('/') {
+        Some(i) => partial.split_at(i + 1),
+        None => ("", partial),
+    };
+
+    let dir = match dir_part.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => match dirs::home_dir() {
+            Some(home) => home.join(rest.trim_start_matches('/')),
+            None => return BTreeMap::new(),
+        },
+        _ => cwd.join(dir_part),
+    };
+
+    std::fs::read_dir(&dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if !name.starts_with(name_part)
+                || (name.starts_with('.') && !name_part.starts_with('.'))
+            {
+                return None;
+            }
+            // Follows symlinks, so a link to a directory completes like one
+            let is_dir = entry.path().is_dir();
+            Some(if is_dir {
+                (format!("{}{}/", dir_part, name), CompletionKind::Directory)
+            } else {
+                (format!("{}{}", dir_part, name), CompletionKind::File)
+            })
+        })
+        .collect()
+}
+
+/// Programs on PATH whose names start with `prefix`. On Windows the PATHEXT
+/// extension is dropped, as it is when typing the command.
+fn executables(prefix: &str) -> Vec<String> {
+    let Some(path) = std::env::var_os("PATH") else {
+        return Vec::new();
+    };
+
+    let mut programs = Vec::new();
+    for dir in std::env::split_paths(&path) {
+        for entry in std::fs::read_dir(&dir).into_iter().flatten().flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if !name.starts_with(prefix) {
+                continue;
+            }
+            if let Some(program) = executable_name(&entry.path(), name) {
+                programs.push(program);
+            }
+        }
+    }
+    programs
+}
+
+#[cfg(unix)]
+fn executable_name(path: &Path, name: String) -> Option<String> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let meta = std::fs::metadata(path).ok()?;
+    (meta.is_file() && meta.permissions().mode() & 0o111 != 0).then_some(name)
+}
+
+#[cfg(not(unix))]
+fn executable_name(path: &Path, _name: String) -> Option<String> {
+    let extension = path.extension()?.to_string_lossy().into_owned();
+    let extensions = std::env::var("PATHEXT").unwrap_or_else(|_| ".COM;.EXE;.BAT;.CMD".to_string());
+    let runnable = extensions
+        .split(';')
+        .any(|ext| ext.trim_start_matches('.').eq_ignore_ascii_case(&extension));
+
+    (runnable && path.is_file())
+        .then(|| path.file_stem())
+        .flatten()
+        .map(|stem| stem.to_string_lossy().into_owned())
+}
+
+/// git's own commands and the user's aliases, or a list of common ones if
+/// git can't be asked.
+async fn git_subcommands() -> Vec<String> {
+    let mut cmd = tokio::process::Command::new("git");
+    cmd.arg("--list-cmds=main,others,alias,nohelpers");
+
+    match process::output(cmd, None, GIT_TIMEOUT).await {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(|line| line.trim().to_string())
+            .filter(|line| !line.is_empty())
+            .collect(),
+        _ => GIT_SUBCOMMANDS.iter().map(|s| s.to_string()).collect(),
+    }
+}
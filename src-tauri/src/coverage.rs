@@ -0,0 +1,530 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::process;
+use crate::toolchains;
+
+/// Each reporting tool is killed past this.
+const REPORT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Which lines and branches of the code ran, gathered over every run of a
+/// build.
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct CoverageReport {
+    /// `gcov`, `llvm-cov`, `coverage.py` or `c8`.
+    pub tool: String,
+    /// Hit counts of the source's executable lines, in line order.
+    pub lines: Vec<LineHits>,
+    pub branches: Vec<BranchHits>,
+    pub lines_found: usize,
+    pub lines_hit: usize,
+    /// The source file's record as an lcov tracefile, for other tools.
+    pub lcov: String,
+    /// Why coverage couldn't be reported; everything else is empty then.
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct LineHits {
+    pub line: u32,
+    pub hits: u64,
+}
+
+/// One outcome of a branch, as lcov's `BRDA` numbers them.
+#[derive(Serialize, Debug, Clone)]
+pub struct BranchHits {
+    pub line: u32,
+    pub block: u32,
+    pub branch: u32,
+    /// `None` when the code holding the branch never ran.
+    pub hits: Option<u64>,
+}
+
+enum Tool {
+    /// C and C++ built with `--coverage`. Counts go to `.gcda` files next to
+    /// the binary.
+    Gcov,
+    /// Rust built with `-C instrument-coverage`. Each run writes a
+    /// `.profraw` file to the build directory.
+    LlvmCov,
+    /// Python run under `coverage run`, from this interpreter.
+    CoveragePy { python: String },
+    /// Node run under `c8`.
+    C8,
+}
+
+/// Coverage measurement for a single-file build: how the code is compiled
+/// and run, and how the counts are turned into a report once it has run.
+pub(crate) struct Coverage {
+    tool: Tool,
+    build_dir: PathBuf,
+    source: PathBuf,
+    binary: PathBuf,
+}
+
+impl Coverage {
+    pub fn new(
+        toolchain: &str,
+        build_dir: &Path,
+        source: &Path,
+        binary: &Path,
+    ) -> Result<Self, String> {
+        let tool = match toolchain {
+            "c" | "c++" => Tool::Gcov,
+            "rust" => Tool::LlvmCov,
+            "python" => Tool::CoveragePy {
+                python: String::new(),
+            },
+            "javascript" => Tool::C8,
+            _ => return Err(format!("Coverage is not available for {}", toolchain)),
+        };
+        Ok(Self {
+            tool,
+            build_dir: build_dir.to_path_buf(),
+            source: source.to_path_buf(),
+            binary: binary.to_path_buf(),
+        })
+    }
+
+    /// Appended to the compile command.
+    pub fn compile_flags(&self) -> &'static [&'static str] {
+        match self.tool {
+            Tool::Gcov => &["--coverage"],
+            Tool::LlvmCov => &["-C", "instrument-coverage"],
+            Tool::CoveragePy { .. } | Tool::C8 => &[],
+        }
+    }
+
+    /// The run command, wrapped in the tool that measures it.
+    pub fn instrument(&mut self, argv: Vec<String>) -> Result<Vec<String>, String> {
+        let build_dir = self.build_dir.to_string_lossy();
+        match &mut self.tool {
+            Tool::Gcov | Tool::LlvmCov => Ok(argv),
+            Tool::CoveragePy { python } => {
+                *python = argv[0].clone();
+                let mut wrapped = vec![
+                    argv[0].clone(),
+                    "-m".to_string(),
+                    "coverage".to_string(),
+                    "run".to_string(),
+                    // Every run of the build adds to the same data
+                    "--append".to_string(),
+                    "--branch".to_string(),
+                    format!("--data-file={}", self.build_dir.join(".coverage").display()),
+                    format!("--source={}", build_dir),
+                ];
+                wrapped.extend(argv.into_iter().skip(1));
+                Ok(wrapped)
+            }
+            Tool::C8 => {
+                let c8 = toolchains::find_program("c8")
+                    .ok_or("Coverage for JavaScript needs c8: `npm install -g c8`".to_string())?;
+                let mut wrapped = vec![
+                    c8.to_string_lossy().into_owned(),
+                    "--reporter=lcovonly".to_string(),
+                    format!(
+                        "--reports-dir={}",
+                        self.build_dir.join("coverage").display()
+                    ),
+                    format!("--temp-directory={}", self.build_dir.join("c8").display()),
+                    "--clean=false".to_string(),
+                    // The source lives in the build directory, outside the
+                    // directory the program runs in
+                    "--allowExternal".to_string(),
+                ];
+                wrapped.extend(argv);
+                Ok(wrapped)
+            }
+        }
+    }
+
+    /// Environment for the run.
+    pub fn env(&self) -> Vec<(String, String)> {
+        match self.tool {
+            Tool::LlvmCov => vec![(
+                "LLVM_PROFILE_FILE".to_string(),
+                self.build_dir
+                    .join("coverage-%p.profraw")
+                    .to_string_lossy()
+                    .into_owned(),
+            )],
+            _ => Vec::new(),
+        }
+    }
+
+    /// Reports the counts of every run so far.
+    pub async fn report(&self) -> CoverageReport {
+        let (tool, lcov) = match &self.tool {
+            Tool::Gcov => ("gcov", self.gcov().await),
+            Tool::LlvmCov => ("llvm-cov", self.llvm_cov().await),
+            Tool::CoveragePy { python } => ("coverage.py", self.coverage_py(python).await),
+            Tool::C8 => (
+                "c8",
+                std::fs::read_to_string(self.build_dir.join("coverage").join("lcov.info"))
+                    .map_err(|_| no_data()),
+            ),
+        };
+
+        let mut report = CoverageReport {
+            tool: tool.to_string(),
+            ..Default::default()
+        };
+        match lcov {
+            Ok(lcov) => {
+                let name = self
+                    .source
+                    .file_name()
+                    .map(|n| n.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                match parse_lcov(&lcov, &name) {
+                    Some(parsed) => {
+                        report.lines_found = parsed.lines.len();
+                        report.lines_hit = parsed.lines.iter().filter(|l| l.hits > 0).count();
+                        report.lines = parsed.lines;
+                        report.branches = parsed.branches;
+                        report.lcov = parsed.record;
+                    }
+                    None => report.error = Some(format!("No coverage was recorded for {}", name)),
+                }
+            }
+            Err(e) => report.error = Some(e),
+        }
+        report
+    }
+
+    /// Converts gcov's JSON intermediate format, which needs gcc 9 or later.
+    async fn gcov(&self) -> Result<String, String> {
+        let gcov = toolchains::find_program("gcov")
+            .ok_or("Coverage for C and C++ needs gcov, which comes with gcc")?;
+        let data = files_with_extension(&self.build_dir, "gcda");
+        if data.is_empty() {
+            return Err(no_data());
+        }
+
+        let mut cmd = tokio::process::Command::new(gcov);
+        cmd.args(["--branch-probabilities", "--json-format", "--stdout"])
+            .args(&data)
+            .current_dir(&self.build_dir);
+        let stdout = run(cmd, "gcov").await?;
+
+        let name = self.source.file_name().unwrap_or_default();
+        let mut lines: BTreeMap<u64, u64> = BTreeMap::new();
+        let mut branches: BTreeMap<u64, Vec<u64>> = BTreeMap::new();
+        // One JSON document per data file
+        for document in stdout.lines().filter(|l| !l.trim().is_empty()) {
+            let json: serde_json::Value = serde_json::from_str(document)
+                .map_err(|e| format!("Unexpected gcov output: {}", e))?;
+            let files = json["files"].as_array().into_iter().flatten();
+            for file in files.filter(|f| {
+                f["file"]
+                    .as_str()
+                    .is_some_and(|file| Path::new(file).file_name() == Some(name))
+            }) {
+                for line in file["lines"].as_array().into_iter().flatten() {
+                    let Some(number) = line["line_number"].as_u64() else {
+                        continue;
+                    };
+                    *lines.entry(number).or_default() += line["count"].as_u64().unwrap_or(0);
+                    let counts = line["branches"]
+                        .as_array()
+                        .into_iter()
+                        .flatten()
+                        .map(|branch| branch["count"].as_u64().unwrap_or(0));
+                    let merged = branches.entry(number).or_default();
+                    for (index, count) in counts.enumerate() {
+                        match merged.get_mut(index) {
+                            Some(total) => *total += count,
+                            None => merged.push(count),
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut lcov = format!("SF:{}\n", self.source.display());
+        for (line, counts) in &branches {
+            let ran = lines.get(line).is_some_and(|&hits| hits > 0);
+            for (index, count) in counts.iter().enumerate() {
+                if ran {
+                    lcov.push_str(&format!("BRDA:{},0,{},{}\n", line, index, count));
+                } else {
+                    lcov.push_str(&format!("BRDA:{},0,{},-\n", line, index));
+                }
+            }
+        }
+        for (line, hits) in &lines {
+            lcov.push_str(&format!("DA:{},{}\n", line, hits));
+        }
+        lcov.push_str("end_of_record\n");
+        Ok(lcov)
+    }
+
+    async fn llvm_cov(&self) -> Result<String, String> {
+        let profiles = files_with_extension(&self.build_dir, "profraw");
+        if profiles.is_empty() {
+            return Err(no_data());
+        }
+        let (Some(profdata), Some(llvm_cov)) = (
+            llvm_tool("llvm-profdata").await,
+            llvm_tool("llvm-cov").await,
+        ) else {
+            return Err(
+                "Coverage for Rust needs the LLVM tools: `rustup component add llvm-tools`"
+                    .to_string(),
+            );
+        };
+
+        let merged = self.build_dir.join("coverage.profdata");
+        let mut cmd = tokio::process::Command::new(profdata);
+        cmd.args(["merge", "-sparse"])
+            .args(&profiles)
+            .arg("-o")
+            .arg(&merged)
+            .current_dir(&self.build_dir);
+        run(cmd, "llvm-profdata").await?;
+
+        let mut cmd = tokio::process::Command::new(llvm_cov);
+        cmd.args(["export", "--format=lcov"])
+            .arg(format!("--instr-profile={}", merged.display()))
+            .arg(&self.binary)
+            .current_dir(&self.build_dir);
+        run(cmd, "llvm-cov").await
+    }
+
+    async fn coverage_py(&self, python: &str) -> Result<String, String> {
+        let data = self.build_dir.join(".coverage");
+        if !data.exists() {
+            return Err(no_data());
+        }
+
+        let lcov = self.build_dir.join("coverage.lcov");
+        let mut cmd = tokio::process::Command::new(python);
+        cmd.args(["-m", "coverage", "lcov", "--quiet"])
+            .arg(format!("--data-file={}", data.display()))
+            .arg("-o")
+            .arg(&lcov)
+            .current_dir(&self.build_dir);
+        run(cmd, "coverage.py").await?;
+        std::fs::read_to_string(&lcov).map_err(|e| e.to_string())
+    }
+}
+
+fn no_data() -> String {
+    "No coverage data was written; counts are saved when the program exits normally".to_string()
+}
+
+/// Runs a reporting tool and returns its stdout, or its first line of
+/// stderr as the error.
+async fn run(cmd: tokio::process::Command, name: &str) -> Result<String, String> {
+    let output = process::output(cmd, None, REPORT_TIMEOUT)
+        .await
+        .map_err(|e| format!("{}: {}", name, e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if stderr.contains("No module named coverage") {
+            return Err(
+                "Coverage for Python needs coverage.py: `pip install coverage`".to_string(),
+            );
+        }
+        let reason = stderr
+            .lines()
+            .find(|l| !l.trim().is_empty())
+            .unwrap_or("failed");
+        return Err(format!("{}: {}", name, reason.trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// An LLVM tool matching rustc's LLVM: the one rustup's `llvm-tools`
+/// component puts in the sysroot, else whichever is on PATH.
+async fn llvm_tool(name: &str) -> Option<PathBuf> {
+    let exe = format!("{}{}", name, std::env::consts::EXE_SUFFIX);
+    if let Some(rustc) = toolchains::find_program("rustc") {
+        let mut cmd = tokio::process::Command::new(rustc);
+        cmd.args(["--print", "sysroot"]);
+        if let Ok(output) = process::output(cmd, None, REPORT_TIMEOUT).await {
+            let sysroot = PathBuf::from(String::from_utf8_lossy(&output.stdout).trim());
+            let hosts = std::fs::read_dir(sysroot.join("lib").join("rustlib"));
+            let found = hosts
+                .into_iter()
+                .flatten()
+                .flatten()
+                .map(|host| host.path().join("bin").join(&exe))
+                .find(|tool| tool.is_file());
+            if found.is_some() {
+                return found;
+            }
+        }
+    }
+    toolchains::find_program(name)
+}
+
+fn files_with_extension(dir: &Path, extension: &str) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|e| e == extension))
+        .collect();
+    files.sort();
+    files
+}
+
+struct ParsedLcov {
+    record: String,
+    lines: Vec<LineHits>,
+    branches: Vec<BranchHits>,
+}
+
+/// The record for the file named `source_name` in an lcov tracefile. Lines
+/// and branches listed more than once have their counts added up.
+fn parse_lcov(lcov: &str, source_name: &str) -> Option<ParsedLcov> {
+    let mut record = String::new();
+    let mut found = false;
+    let mut in_source = false;
+    let mut lines: BTreeMap<u32, u64> = BTreeMap::new();
+    let mut branches: BTreeMap<(u32, u32, u32), Option<u64>> = BTreeMap::new();
+
+    for line in lcov.lines() {
+        let line = line.trim();
+        if let Some(file) = line.strip_prefix("SF:") {
+            in_source = Path::new(file)
+                .file_name()
+                .is_some_and(|n| n.to_string_lossy() == source_name);
+            found |= in_source;
+        }
+        if !in_source {
+            continue;
+        }
+        record.push_str(line);
+        record.push('\n');
+
+        if let Some(data) = line.strip_prefix("DA:") {
+            // coverage.py adds a checksum as a third field
+            let mut fields = data.split(',');
+            let number = fields.next().and_then(|n| n.parse().ok());
+            let hits = fields.next().and_then(|h| h.parse::<u64>().ok());
+            if let (Some(number), Some(hits)) = (number, hits) {
+                *lines.entry(number).or_default() += hits;
+            }
+        } else if let Some(data) = line.strip_prefix("BRDA:") {
+            let fields: Vec<&str> = data.split(',').collect();
+            let numbers: Vec<Option<u32>> = fields.iter().take(3).map(|f| f.parse().ok()).collect();
+            if let ([Some(number), Some(block), Some(branch)], Some(taken)) =
+                (numbers.as_slice(), fields.get(3))
+            {
+                let hits = taken.parse::<u64>().ok();
+                let entry = branches.entry((*number, *block, *branch)).or_default();
+                *entry = match (*entry, hits) {
+                    (Some(a), Some(b)) => Some(a + b),
+                    (a, b) => a.or(b),
+                };
+            }
+        } else if line == "end_of_record" {
+            in_source = false;
+        }
+    }
+
+    found.then(|| ParsedLcov {
+        record,
+        lines: lines
+            .into_iter()
+            .map(|(line, hits)| LineHits { line, hits })
+            .collect(),
+        branches: branches
+            .into_iter()
+            .map(|((line, block, branch), hits)| BranchHits {
+                line,
+                block,
+                branch,
+                hits,
+            })
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LCOV: &str = "\
+TN:
+SF:/tmp/build/main.c
+FN:2,square
+FNDA:3,square
+DA:2,3
+DA:3,3
+DA:5,1
+BRDA:3,0,0,2
+BRDA:3,0,1,1
+BRDA:7,0,0,-
+BRDA:7,0,1,-
+LF:3
+LH:3
+end_of_record
+SF:/usr/include/stdio.h
+DA:10,4
+end_of_record
+SF:/tmp/build/main.c
+DA:3,2
+DA:9,0
+BRDA:3,0,0,1
+BRDA:7,0,0,1
+end_of_record
+";
+
+    fn hits(parsed: &ParsedLcov) -> Vec<(u32, u64)> {
+        parsed.lines.iter().map(|l| (l.line, l.hits)).collect()
+    }
+
+    #[test]
+    fn keeps_only_the_source_files_record() {
+        let parsed = parse_lcov(LCOV, "main.c").unwrap();
+        assert!(parsed
+            .record
+            .starts_with("SF:/tmp/build/main.c\nFN:2,square\n"));
+        assert!(!parsed.record.contains("stdio.h"));
+        assert!(!parsed.record.contains("TN:"));
+        assert_eq!(parsed.record.matches("end_of_record").count(), 2);
+
+        let parsed = parse_lcov(LCOV, "stdio.h").unwrap();
+        assert_eq!(hits(&parsed), [(10, 4)]);
+    }
+
+    #[test]
+    fn adds_up_repeated_counts() {
+        let parsed = parse_lcov(LCOV, "main.c").unwrap();
+        assert_eq!(hits(&parsed), [(2, 3), (3, 5), (5, 1), (9, 0)]);
+
+        let branches: Vec<(u32, u32, u32, Option<u64>)> = parsed
+            .branches
+            .iter()
+            .map(|b| (b.line, b.block, b.branch, b.hits))
+            .collect();
+        assert_eq!(
+            branches,
+            [
+                (3, 0, 0, Some(3)),
+                (3, 0, 1, Some(1)),
+                (7, 0, 0, Some(1)),
+                (7, 0, 1, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_coverage_py_checksums() {
+        let lcov = "SF:main.py\nDA:1,1,7x3JxV0bxjE3uVrlhYJ+Zg\nDA:2,0,Ah6h1JxqLbJ6ddwSIUjYTw\nend_of_record\n";
+        let parsed = parse_lcov(lcov, "main.py").unwrap();
+        assert_eq!(hits(&parsed), [(1, 1), (2, 0)]);
+    }
+
+    #[test]
+    fn misses_files_that_are_not_there() {
+        assert!(parse_lcov(LCOV, "other.c").is_none());
+        assert!(parse_lcov("", "main.c").is_none());
+    }
+}
@@ -0,0 +1,1164 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::ChildStdin;
+use tokio::sync::oneshot;
+
+use crate::execution::{AppState, ExecutionResult, PreparedRun, RunRegistry};
+use crate::process::{self, Process};
+use crate::run_config::RunConfig;
+use crate::sandbox::ExecutionMode;
+use crate::settings;
+use crate::toolchains::{self, BuildProfile};
+
+static NEXT_SESSION_ID: AtomicU64 = AtomicU64::new(1);
+
+/// How long gdb gets to answer a command. Starting the program loads its
+/// symbols, which can take a while for large binaries.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long `-gdb-exit` gets before gdb is killed.
+const EXIT_GRACE: Duration = Duration::from_secs(2);
+
+/// Programs running under gdb, driven over its machine interface (GDB/MI).
+///
+/// Events carry the session id: `debug-stopped` when the program stops at a
+/// breakpoint, after a step or on a signal such as SIGSEGV, `debug-running`
+/// when it resumes, `debug-output` with what it prints, `debug-console` with
+/// gdb's own messages, `debug-exited` when it exits, and `debug-ended` once
+/// the session is gone.
+#[derive(Default)]
+pub struct DebugSessions {
+    sessions: Mutex<HashMap<u64, Arc<DebugSession>>>,
+}
+
+struct DebugSession {
+    id: u64,
+    stdin: tokio::sync::Mutex<ChildStdin>,
+    next_token: AtomicU64,
+    /// Requests waiting for gdb's `^` result record, by token.
+    pending: Mutex<HashMap<u64, oneshot::Sender<MiResult>>>,
+    gdb_pid: u32,
+    /// Set once gdb reports the program's process.
+    inferior_pid: AtomicU32,
+    ended: AtomicBool,
+    source: PathBuf,
+    /// Generated line to pseudocode line.
+    line_map: BTreeMap<u32, u32>,
+    breakpoints: Mutex<Vec<Breakpoint>>,
+    /// The thread of the last stop, which stack and variable queries refer
+    /// to.
+    thread: Mutex<Option<String>>,
+    /// The program's terminal. Held so it stays open between the program's
+    /// writes; dropped with the session, which ends its reader.
+    #[cfg(unix)]
+    tty: Mutex<Option<InferiorTty>>,
+    /// The build, released when the session ends.
+    build: Mutex<Option<PreparedRun>>,
+}
+
+#[cfg(unix)]
+struct InferiorTty {
+    _master: Box<dyn portable_pty::MasterPty + Send>,
+    _slave: Box<dyn portable_pty::SlavePty + Send>,
+    writer: Box<dyn std::io::Write + Send>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct Breakpoint {
+    /// gdb's breakpoint number.
+    pub number: u32,
+    /// The generated line the breakpoint ended up on, which may be after
+    /// the requested one when that line has no code.
+    pub line: Option<u32>,
+    pub pseudocode_line: Option<u32>,
+    pub function: Option<String>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct Frame {
+    pub level: u32,
+    pub function: Option<String>,
+    pub file: Option<String>,
+    pub line: Option<u32>,
+    pub pseudocode_line: Option<u32>,
+    pub address: Option<String>,
+    /// The frame is in the debugged code rather than a library.
+    pub user_code: bool,
+}
+
+#[derive(Serialize, Clone)]
+pub struct Variable {
+    pub name: String,
+    pub type_name: Option<String>,
+    pub value: Option<String>,
+    /// A function argument rather than a local.
+    pub argument: bool,
+}
+
+#[derive(Serialize)]
+pub struct DebugStart {
+    /// Unset when the build failed.
+    pub session: Option<u64>,
+    pub build_error: Option<ExecutionResult>,
+    pub breakpoints: Vec<Breakpoint>,
+}
+
+#[derive(Clone, Serialize)]
+struct DebugStopped {
+    session: u64,
+    /// gdb's stop reason, e.g. `breakpoint-hit`, `end-stepping-range`,
+    /// `function-finished` or `signal-received`.
+    reason: Option<String>,
+    frame: Option<Frame>,
+    breakpoint: Option<u32>,
+    signal: Option<String>,
+    signal_meaning: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+struct DebugSessionEvent {
+    session: u64,
+}
+
+#[derive(Clone, Serialize)]
+struct DebugText {
+    session: u64,
+    data: String,
+}
+
+#[derive(Clone, Serialize)]
+struct DebugExited {
+    session: u64,
+    exit_code: Option<i32>,
+    signal: Option<String>,
+}
+
+/// Generated lines to pseudocode lines, sent as a JSON object.
+#[derive(Deserialize, Default)]
+#[serde(transparent)]
+pub struct LineMap(BTreeMap<u32, u32>);
+
+// --- GDB/MI output ---
+
+#[derive(Debug, Clone)]
+enum MiValue {
+    Const(String),
+    Tuple(Vec<(String, MiValue)>),
+    /// Lists of results (`[frame={...},frame={...}]`) keep only the values.
+    List(Vec<MiValue>),
+}
+
+impl MiValue {
+    fn get(&self, key: &str) -> Option<&MiValue> {
+        match self {
+            MiValue::Tuple(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    fn str(&self, key: &str) -> Option<&str> {
+        match self.get(key)? {
+            MiValue::Const(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    fn list(&self, key: &str) -> &[MiValue] {
+        match self.get(key) {
+            Some(MiValue::List(items)) => items,
+            _ => &[],
+        }
+    }
+}
+
+struct MiResult {
+    class: String,
+    results: MiValue,
+}
+
+enum MiRecord {
+    Result {
+        token: Option<u64>,
+        result: MiResult,
+    },
+    /// `*` (exec), `+` (status) and `=` (notify) records.
+    Async {
+        class: String,
+        results: MiValue,
+    },
+    /// `~` (console), `@` (target) and `&` (log) output.
+    Stream {
+        kind: char,
+        text: String,
+    },
+    Prompt,
+    /// Anything else is the program's own output, when it shares gdb's.
+    Other(String),
+}
+
+fn parse_record(line: &str) -> MiRecord {
+    let digits = line.bytes().take_while(u8::is_ascii_digit).count();
+    let token = line[..digits].parse().ok();
+    let rest = &line[digits..];
+
+    if line.trim_end() == "(gdb)" {
+        return MiRecord::Prompt;
+    }
+    let Some(kind) = rest.chars().next() else {
+        return MiRecord::Other(line.to_string());
+    };
+    let body = &rest[kind.len_utf8()..];
+
+    match kind {
+        '^' | '*' | '+' | '=' => {
+            let (class, results) = match body.split_once(',') {
+                Some((class, results)) => (class, parse_results(results)),
+                None => (body, Some(MiValue::Tuple(Vec::new()))),
+            };
+            let Some(results) = results else {
+                return MiRecord::Other(line.to_string());
+            };
+            let class = class.to_string();
+            if kind == '^' {
+                MiRecord::Result {
+                    token,
+                    result: MiResult { class, results },
+                }
+            } else {
+                MiRecord::Async { class, results }
+            }
+        }
+        '~' | '@' | '&' if token.is_none() => match Parser::new(body).c_string() {
+            Some(text) => MiRecord::Stream { kind, text },
+            None => MiRecord::Other(line.to_string()),
+        },
+        _ => MiRecord::Other(line.to_string()),
+    }
+}
+
+/// Parses `a="1",b={...}` into a tuple.
+fn parse_results(text: &str) -> Option<MiValue> {
+    let mut parser = Parser::new(text);
+    let mut fields = Vec::new();
+    loop {
+        fields.push(parser.result()?);
+        if !parser.eat(b',') {
+            break;
+        }
+    }
+    parser.at_end().then_some(MiValue::Tuple(fields))
+}
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(text: &'a str) -> Self {
+        Self {
+            bytes: text.as_bytes(),
+            pos: 0,
+        }
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos == self.bytes.len()
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, byte: u8) -> bool {
+        let found = self.peek() == Some(byte);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn result(&mut self) -> Option<(String, MiValue)> {
+        let start = self.pos;
+        while self.peek().is_some_and(|b| b != b'=') {
+            self.pos += 1;
+        }
+        let name = std::str::from_utf8(&self.bytes[start..self.pos])
+            .ok()?
+            .to_string();
+        self.eat(b'=').then_some(())?;
+        Some((name, self.value()?))
+    }
+
+    fn value(&mut self) -> Option<MiValue> {
+        match self.peek()? {
+            b'"' => self.c_string().map(MiValue::Const),
+            b'{' => {
+                self.pos += 1;
+                let mut fields = Vec::new();
+                if !self.eat(b'}') {
+                    loop {
+                        fields.push(self.result()?);
+                        if self.eat(b'}') {
+                            break;
+                        }
+                        self.eat(b',').then_some(())?;
+                    }
+                }
+                Some(MiValue::Tuple(fields))
+            }
+            b'[' => {
+                self.pos += 1;
+                let mut items = Vec::new();
+                if !self.eat(b']') {
+                    loop {
+                        let item = match self.peek()? {
+                            b'"' | b'{' | b'[' => self.value()?,
+                            _ => self.result()?.1,
+                        };
+                        items.push(item);
+                        if self.eat(b']') {
+                            break;
+                        }
+                        self.eat(b',').then_some(())?;
+                    }
+                }
+                Some(MiValue::List(items))
+            }
+            _ => None,
+        }
+    }
+
+    /// A C-style quoted string. gdb writes non-ASCII bytes as octal
+    /// escapes, so the bytes are collected before decoding.
+    fn c_string(&mut self) -> Option<String> {
+        self.eat(b'"').then_some(())?;
+        let mut out = Vec::new();
+        loop {
+            let byte = self.peek()?;
+            self.pos += 1;
+            match byte {
+                b'"' => break,
+                b'\\' => {
+                    let escaped = self.peek()?;
+                    self.pos += 1;
+                    out.push(match escaped {
+                        b'n' => b'\n',
+                        b't' => b'\t',
+                        b'r' => b'\r',
+                        b'e' => 0x1b,
+                        b'0'..=b'7' => {
+                            let mut value = (escaped - b'0') as u32;
+                            for _ in 0..2 {
+                                match self.peek() {
+                                    Some(d @ b'0'..=b'7') => {
+                                        value = value * 8 + (d - b'0') as u32;
+                                        self.pos += 1;
+                                    }
+                                    _ => break,
+                                }
+                            }
+                            value as u8
+                        }
+                        other => other,
+                    });
+                }
+                other => out.push(other),
+            }
+        }
+        Some(String::from_utf8_lossy(&out).into_owned())
+    }
+}
+
+/// Quotes an argument for an MI command.
+fn mi_quote(text: &str) -> String {
+    let escaped = text.replace('\\', "\\\\").replace('"', "\\\"");
+    format!("\"{}\"", escaped)
+}
+
+// --- Sessions ---
+
+impl DebugSession {
+    /// Sends an MI command and waits for its result record.
+    async fn request(&self, command: &str) -> Result<MiValue, String> {
+        if self.ended.load(Ordering::SeqCst) {
+            return Err("The debug session has ended".to_string());
+        }
+
+        let token = self.next_token.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = oneshot::channel();
+        self.pending
+            .lock()
+            .map_err(|e| e.to_string())?
+            .insert(token, tx);
+
+        let line = format!("{}{}\n", token, command);
+        let written = {
+            let mut stdin = self.stdin.lock().await;
+            match stdin.write_all(line.as_bytes()).await {
+                Ok(()) => stdin.flush().await,
+                Err(e) => Err(e),
+            }
+        };
+        if let Err(e) = written {
+            self.forget(token);
+            return Err(format!("Failed to send command to gdb: {}", e));
+        }
+
+        let result = match tokio::time::timeout(REQUEST_TIMEOUT, rx).await {
+            Ok(Ok(result)) => result,
+            Ok(Err(_)) => return Err("gdb exited".to_string()),
+            Err(_) => {
+                self.forget(token);
+                return Err(format!("gdb did not answer '{}' in time", command));
+            }
+        };
+
+        match result.class.as_str() {
+            "error" => Err(result
+                .results
+                .str("msg")
+                .unwrap_or("gdb reported an error")
+                .to_string()),
+            _ => Ok(result.results),
+        }
+    }
+
+    fn forget(&self, token: u64) {
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.remove(&token);
+        }
+    }
+
+    /// The pseudocode line of a generated line: that of the nearest mapped
+    /// line at or above it.
+    fn pseudocode_line(&self, line: Option<u32>) -> Option<u32> {
+        let line = line?;
+        self.line_map.range(..=line).next_back().map(|(_, &p)| p)
+    }
+
+    fn is_source(&self, file: &str) -> bool {
+        let path = Path::new(file);
+        path == self.source
+            || std::fs::canonicalize(path)
+                .ok()
+                .zip(std::fs::canonicalize(&self.source).ok())
+                .is_some_and(|(a, b)| a == b)
+    }
+
+    fn frame(&self, frame: &MiValue) -> Frame {
+        let line = frame.str("line").and_then(|l| l.parse().ok());
+        let user_code = frame.str("fullname").is_some_and(|f| self.is_source(f));
+        Frame {
+            level: frame.str("level").and_then(|l| l.parse().ok()).unwrap_or(0),
+            function: frame.str("func").map(String::from),
+            file: frame.str("file").map(String::from),
+            line,
+            pseudocode_line: if user_code {
+                self.pseudocode_line(line)
+            } else {
+                None
+            },
+            address: frame.str("addr").map(String::from),
+            user_code,
+        }
+    }
+
+    fn breakpoint(&self, bkpt: &MiValue) -> Option<Breakpoint> {
+        let line = bkpt.str("line").and_then(|l| l.parse().ok());
+        Some(Breakpoint {
+            number: bkpt.str("number")?.parse().ok()?,
+            line,
+            pseudocode_line: self.pseudocode_line(line),
+            function: bkpt.str("func").map(String::from),
+        })
+    }
+
+    async fn insert_breakpoint(&self, line: u32) -> Result<Breakpoint, String> {
+        let location = format!("{}:{}", self.source.to_string_lossy(), line);
+        let results = self
+            .request(&format!("-break-insert {}", mi_quote(&location)))
+            .await?;
+        let breakpoint = results
+            .get("bkpt")
+            .and_then(|bkpt| self.breakpoint(bkpt))
+            .ok_or("gdb did not describe the breakpoint")?;
+
+        if let Ok(mut breakpoints) = self.breakpoints.lock() {
+            breakpoints.push(breakpoint.clone());
+        }
+        Ok(breakpoint)
+    }
+
+    fn thread(&self) -> Result<String, String> {
+        self.thread
+            .lock()
+            .map_err(|e| e.to_string())?
+            .clone()
+            .ok_or_else(|| "The program is not stopped".to_string())
+    }
+
+    fn kill(&self) {
+        // The program is in gdb's process group unless it moved to a session
+        // of its own on its terminal, so it is also killed by pid
+        let inferior = self.inferior_pid.load(Ordering::SeqCst);
+        if inferior != 0 {
+            let _ = process::kill(inferior);
+        }
+        let _ = process::kill_tree(self.gdb_pid);
+    }
+
+    /// Handles a line of gdb's output.
+    fn handle(&self, app: &AppHandle, line: &str) {
+        match parse_record(line) {
+            MiRecord::Result { token, result } => {
+                let waiter = token.and_then(|token| {
+                    self.pending
+                        .lock()
+                        .ok()
+                        .and_then(|mut pending| pending.remove(&token))
+                });
+                if let Some(waiter) = waiter {
+                    let _ = waiter.send(result);
+                }
+            }
+            MiRecord::Async { class, results } => self.handle_async(app, &class, &results),
+            MiRecord::Stream { kind: '@', text } => self.emit_text(app, "debug-output", text),
+            MiRecord::Stream { text, .. } => self.emit_text(app, "debug-console", text),
+            MiRecord::Prompt => {}
+            MiRecord::Other(text) => self.emit_text(app, "debug-output", text + "\n"),
+        }
+    }
+
+    fn handle_async(&self, app: &AppHandle, class: &str, results: &MiValue) {
+        match class {
+            "running" => {
+                let _ = app.emit("debug-running", DebugSessionEvent { session: self.id });
+            }
+            "thread-group-started" => {
+                if let Some(pid) = results.str("pid").and_then(|p| p.parse().ok()) {
+                    self.inferior_pid.store(pid, Ordering::SeqCst);
+                }
+            }
+            "stopped" => {
+                let reason = results.str("reason");
+                if let Some(reason) = reason.filter(|r| r.starts_with("exited")) {
+                    self.inferior_pid.store(0, Ordering::SeqCst);
+                    // gdb reports exit codes in octal
+                    let exit_code = match reason {
+                        "exited-normally" => Some(0),
+                        _ => results
+                            .str("exit-code")
+                            .and_then(|code| i32::from_str_radix(code, 8).ok()),
+                    };
+                    let _ = app.emit(
+                        "debug-exited",
+                        DebugExited {
+                            session: self.id,
+                            exit_code,
+                            signal: results.str("signal-name").map(String::from),
+                        },
+                    );
+                    return;
+                }
+
+                if let Ok(mut thread) = self.thread.lock() {
+                    *thread = results.str("thread-id").map(String::from);
+                }
+                let _ = app.emit(
+                    "debug-stopped",
+                    DebugStopped {
+                        session: self.id,
+                        reason: reason.map(String::from),
+                        frame: results.get("frame").map(|frame| self.frame(frame)),
+                        breakpoint: results.str("bkptno").and_then(|n| n.parse().ok()),
+                        signal: results.str("signal-name").map(String::from),
+                        signal_meaning: results.str("signal-meaning").map(String::from),
+                    },
+                );
+            }
+            _ => {}
+        }
+    }
+
+    fn emit_text(&self, app: &AppHandle, event: &str, data: String) {
+        let _ = app.emit(
+            event,
+            DebugText {
+                session: self.id,
+                data,
+            },
+        );
+    }
+
+    /// Called once gdb has exited: fails outstanding requests, frees the
+    /// build and tells the frontend.
+    fn end(&self, app: &AppHandle) {
+        self.ended.store(true, Ordering::SeqCst);
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.clear();
+        }
+        #[cfg(unix)]
+        if let Ok(mut tty) = self.tty.lock() {
+            tty.take();
+        }
+        let build = self.build.lock().ok().and_then(|mut build| build.take());
+        if let Some(build) = build {
+            build.release(app);
+        }
+        let _ = app.emit("debug-ended", DebugSessionEvent { session: self.id });
+    }
+}
+
+impl DebugSessions {
+    fn get(&self, id: u64) -> Result<Arc<DebugSession>, String> {
+        self.sessions
+            .lock()
+            .map_err(|e| e.to_string())?
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| format!("No debug session {}", id))
+    }
+
+    fn remove(&self, id: u64) {
+        if let Ok(mut sessions) = self.sessions.lock() {
+            sessions.remove(&id);
+        }
+    }
+
+    /// Kills every debugged program and its gdb. Called on app exit.
+    pub fn close_all(&self) {
+        if let Ok(mut sessions) = self.sessions.lock() {
+            for (_, session) in sessions.drain() {
+                session.kill();
+            }
+        }
+    }
+}
+
+/// Opens a pseudo-terminal for the program, so it sees a terminal as it
+/// would in a normal run, and forwards what it prints as `debug-output`.
+#[cfg(unix)]
+fn open_inferior_tty(app: &AppHandle, session: u64) -> Result<(InferiorTty, PathBuf), String> {
+    use std::io::Read;
+
+    let pair = portable_pty::native_pty_system()
+        .openpty(portable_pty::PtySize::default())
+        .map_err(|e| format!("Failed to open a pseudo-terminal: {}", e))?;
+    let tty_name = pair
+        .master
+        .tty_name()
+        .ok_or("Failed to name the pseudo-terminal")?;
+    let mut reader = pair.master.try_clone_reader().map_err(|e| e.to_string())?;
+    let writer = pair.master.take_writer().map_err(|e| e.to_string())?;
+
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let mut buf = [0u8; 4096];
+        // Ends once the session drops both ends of the terminal
+        while let Ok(n) = reader.read(&mut buf) {
+            if n == 0 {
+                break;
+            }
+            let _ = app.emit(
+                "debug-output",
+                DebugText {
+                    session,
+                    data: String::from_utf8_lossy(&buf[..n]).into_owned(),
+                },
+            );
+        }
+    });
+
+    Ok((
+        InferiorTty {
+            _master: pair.master,
+            _slave: pair.slave,
+            writer,
+        },
+        tty_name,
+    ))
+}
+
+/// Builds the code with the toolchain's debug flags and starts it under
+/// gdb, stopping at `breakpoints` (generated lines). `line_map` maps
+/// generated lines to pseudocode lines for the frames and breakpoints
+/// reported back. Compiler output streams like `execute_code`'s; a failed
+/// build is returned as `build_error`.
+#[tauri::command]
+pub async fn start_debug_session(
+    app: AppHandle,
+    language: String,
+    code: String,
+    breakpoints: Vec<u32>,
+    line_map: Option<LineMap>,
+    state: State<'_, AppState>,
+    sessions: State<'_, DebugSessions>,
+) -> Result<DebugStart, String> {
+    let cwd = state.cwd.lock().map_err(|e| e.to_string())?.clone();
+    if settings::load(&cwd)?.execution.mode == ExecutionMode::Sandbox {
+        // gdb and the program would run outside the sandbox, without its
+        // limits or timeout
+        return Err("Debugging is not available in sandbox mode".to_string());
+    }
+    let gdb = toolchains::find_program("gdb")
+        .ok_or("gdb was not found. Install gdb or add it to PATH to debug programs")?;
+
+    let config = RunConfig {
+        profile: Some(BuildProfile::Debug),
+        ..RunConfig::default()
+    };
+    let prepared = PreparedRun::new(&app, &cwd, &language, &code, config)?;
+    let registry = app.state::<RunRegistry>();
+    registry.insert(prepared.run_id, prepared.initial_status());
+    let built = prepared.build(&app).await;
+    registry.remove(prepared.run_id);
+    if let Err(failed) = built {
+        prepared.release(&app);
+        return Ok(DebugStart {
+            session: None,
+            build_error: Some(failed),
+            breakpoints: Vec::new(),
+        });
+    }
+
+    let Some(source) = prepared.source().map(Path::to_path_buf) else {
+        prepared.release(&app);
+        return Err("Only single files can be debugged".to_string());
+    };
+    let mut cmd = tokio::process::Command::new(gdb);
+    cmd.args(["--interpreter=mi2", "--quiet", "--args"])
+        .args(prepared.run_argv())
+        .current_dir(prepared.run_dir());
+    #[cfg(target_os = "windows")]
+    cmd.creation_flags(0x08000000);
+
+    let id = NEXT_SESSION_ID.fetch_add(1, Ordering::Relaxed);
+    #[cfg(unix)]
+    let (tty, tty_name) = match open_inferior_tty(&app, id) {
+        Ok(tty) => tty,
+        Err(e) => {
+            prepared.release(&app);
+            return Err(e);
+        }
+    };
+    let mut gdb = match Process::spawn(cmd, true) {
+        Ok(gdb) => gdb,
+        Err(e) => {
+            prepared.release(&app);
+            return Err(format!("Failed to start gdb: {}", e));
+        }
+    };
+
+    let session = Arc::new(DebugSession {
+        id,
+        stdin: tokio::sync::Mutex::new(gdb.stdin.take().ok_or("gdb has no stdin")?),
+        next_token: AtomicU64::new(1),
+        pending: Mutex::new(HashMap::new()),
+        gdb_pid: gdb.pid,
+        inferior_pid: AtomicU32::new(0),
+        ended: AtomicBool::new(false),
+        source,
+        line_map: line_map.unwrap_or_default().0,
+        breakpoints: Mutex::new(Vec::new()),
+        thread: Mutex::new(None),
+        #[cfg(unix)]
+        tty: Mutex::new(Some(tty)),
+        build: Mutex::new(Some(prepared)),
+    });
+    sessions
+        .sessions
+        .lock()
+        .map_err(|e| e.to_string())?
+        .insert(id, session.clone());
+
+    if let Some(stderr) = gdb.stderr.take() {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            let mut lines = BufReader::new(stderr).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                let _ = app.emit(
+                    "debug-console",
+                    DebugText {
+                        session: id,
+                        data: line + "\n",
+                    },
+                );
+            }
+        });
+    }
+
+    let stdout = gdb.stdout.take().ok_or("gdb has no stdout")?;
+    let reader_app = app.clone();
+    let reader_session = session.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut lines = BufReader::new(stdout).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            reader_session.handle(&reader_app, &line);
+        }
+        let _ = gdb.wait(None).await;
+        reader_app.state::<DebugSessions>().remove(id);
+        reader_session.end(&reader_app);
+    });
+
+    let setup = async {
+        session.request("-gdb-set mi-async on").await?;
+        // Not every gdb has Python for pretty printers
+        let _ = session.request("-enable-pretty-printing").await;
+        #[cfg(unix)]
+        session
+            .request(&format!(
+                "-inferior-tty-set {}",
+                mi_quote(&tty_name.to_string_lossy())
+            ))
+            .await?;
+
+        let mut inserted = Vec::new();
+        for line in breakpoints {
+            inserted.push(session.insert_breakpoint(line).await?);
+        }
+        session.request("-exec-run").await?;
+        Ok::<_, String>(inserted)
+    };
+
+    match setup.await {
+        Ok(breakpoints) => Ok(DebugStart {
+            session: Some(id),
+            build_error: None,
+            breakpoints,
+        }),
+        Err(e) => {
+            session.kill();
+            Err(e)
+        }
+    }
+}
+
+/// Adds a breakpoint on a generated line, whether the program is running
+/// or stopped.
+#[tauri::command]
+pub async fn debug_set_breakpoint(
+    session: u64,
+    line: u32,
+    sessions: State<'_, DebugSessions>,
+) -> Result<Breakpoint, String> {
+    let session = sessions.get(session)?;
+    session.insert_breakpoint(line).await
+}
+
+/// Removes the breakpoints on a generated line.
+#[tauri::command]
+pub async fn debug_remove_breakpoint(
+    session: u64,
+    line: u32,
+    sessions: State<'_, DebugSessions>,
+) -> Result<(), String> {
+    let session = sessions.get(session)?;
+    let numbers: Vec<u32> = session
+        .breakpoints
+        .lock()
+        .map_err(|e| e.to_string())?
+        .iter()
+        .filter(|b| b.line == Some(line))
+        .map(|b| b.number)
+        .collect();
+
+    for number in &numbers {
+        session
+            .request(&format!("-break-delete {}", number))
+            .await?;
+    }
+    if let Ok(mut breakpoints) = session.breakpoints.lock() {
+        breakpoints.retain(|b| !numbers.contains(&b.number));
+    }
+    Ok(())
+}
+
+/// The session's breakpoints, in the order they were set.
+#[tauri::command]
+pub fn debug_breakpoints(
+    session: u64,
+    sessions: State<'_, DebugSessions>,
+) -> Result<Vec<Breakpoint>, String> {
+    let session = sessions.get(session)?;
+    let breakpoints = session.breakpoints.lock().map_err(|e| e.to_string())?;
+    Ok(breakpoints.clone())
+}
+
+/// Sends an execution command. Its effect arrives as `debug-running` and
+/// then `debug-stopped` or `debug-exited`.
+async fn exec(sessions: &DebugSessions, session: u64, command: &str) -> Result<(), String> {
+    sessions.get(session)?.request(command).await.map(|_| ())
+}
+
+#[tauri::command]
+pub async fn debug_continue(
+    session: u64,
+    sessions: State<'_, DebugSessions>,
+) -> Result<(), String> {
+    exec(&sessions, session, "-exec-continue").await
+}
+
+#[tauri::command]
+pub async fn debug_step_over(
+    session: u64,
+    sessions: State<'_, DebugSessions>,
+) -> Result<(), String> {
+    exec(&sessions, session, "-exec-next").await
+}
+
+#[tauri::command]
+pub async fn debug_step_into(
+    session: u64,
+    sessions: State<'_, DebugSessions>,
+) -> Result<(), String> {
+    exec(&sessions, session, "-exec-step").await
+}
+
+/// Runs until the current function returns. Fails in the outermost frame.
+#[tauri::command]
+pub async fn debug_step_out(
+    session: u64,
+    sessions: State<'_, DebugSessions>,
+) -> Result<(), String> {
+    exec(&sessions, session, "-exec-finish").await
+}
+
+/// Stops the running program wherever it is.
+#[tauri::command]
+pub async fn debug_pause(session: u64, sessions: State<'_, DebugSessions>) -> Result<(), String> {
+    exec(&sessions, session, "-exec-interrupt").await
+}
+
+/// The stopped thread's call stack, innermost frame first.
+#[tauri::command]
+pub async fn debug_stack(
+    session: u64,
+    sessions: State<'_, DebugSessions>,
+) -> Result<Vec<Frame>, String> {
+    let session = sessions.get(session)?;
+    let thread = session.thread()?;
+    let results = session
+        .request(&format!("-stack-list-frames --thread {}", thread))
+        .await?;
+    Ok(results
+        .list("stack")
+        .iter()
+        .map(|frame| session.frame(frame))
+        .collect())
+}
+
+/// Arguments and locals of a frame of the stopped thread (0 is innermost).
+/// Structs and arrays are printed whole.
+#[tauri::command]
+pub async fn debug_locals(
+    session: u64,
+    frame: u32,
+    sessions: State<'_, DebugSessions>,
+) -> Result<Vec<Variable>, String> {
+    let session = sessions.get(session)?;
+    let thread = session.thread()?;
+    let scope = format!("--thread {} --frame {}", thread, frame);
+    let results = session
+        .request(&format!("-stack-list-variables {} --simple-values", scope))
+        .await?;
+
+    let mut variables = Vec::new();
+    for var in results.list("variables") {
+        let Some(name) = var.str("name") else {
+            continue;
+        };
+        // Only scalars come with a value; the rest are evaluated one by one
+        let value = match var.str("value") {
+            Some(value) => Some(value.to_string()),
+            None => session
+                .request(&format!(
+                    "-data-evaluate-expression {} {}",
+                    scope,
+                    mi_quote(name)
+                ))
+                .await
+                .ok()
+                .and_then(|results| results.str("value").map(String::from)),
+        };
+        variables.push(Variable {
+            name: name.to_string(),
+            type_name: var.str("type").map(String::from),
+            value,
+            argument: var.str("arg") == Some("1"),
+        });
+    }
+    Ok(variables)
+}
+
+/// Sends input to the debugged program's stdin.
+#[tauri::command]
+pub fn debug_write_input(
+    session: u64,
+    data: String,
+    sessions: State<'_, DebugSessions>,
+) -> Result<(), String> {
+    let session = sessions.get(session)?;
+    #[cfg(unix)]
+    {
+        use std::io::Write;
+
+        let mut tty = session.tty.lock().map_err(|e| e.to_string())?;
+        let tty = tty.as_mut().ok_or("The debug session has ended")?;
+        tty.writer
+            .write_all(data.as_bytes())
+            .and_then(|_| tty.writer.flush())
+            .map_err(|e| format!("Failed to write to the program: {}", e))
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = (session, data);
+        Err("Input to debugged programs is not supported on this platform".to_string())
+    }
+}
+
+/// Ends a session, killing the program. `debug-ended` follows once gdb is
+/// gone.
+#[tauri::command]
+pub async fn stop_debug_session(
+    session: u64,
+    sessions: State<'_, DebugSessions>,
+) -> Result<(), String> {
+    let session = sessions.get(session)?;
+    if session.request("-gdb-exit").await.is_err() {
+        session.kill();
+        return Ok(());
+    }
+
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(EXIT_GRACE).await;
+        if !session.ended.load(Ordering::SeqCst) {
+            session.kill();
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result_record(line: &str) -> (Option<u64>, MiResult) {
+        match parse_record(line) {
+            MiRecord::Result { token, result } => (token, result),
+            _ => panic!("not a result record: {}", line),
+        }
+    }
+
+    fn is_other(line: &str) -> bool {
+        matches!(parse_record(line), MiRecord::Other(text) if text == line)
+    }
+
+    #[test]
+    fn parses_result_records_with_tokens() {
+        let (token, result) = result_record(
+            r#"12^done,bkpt={number="1",type="breakpoint",disp="keep",enabled="y",addr="0x0000000000001139",func="main",file="main.c",fullname="/tmp/main.c",line="5",thread-groups=["i1"],times="0",original-location="main.c:5"}"#,
+        );
+        assert_eq!(token, Some(12));
+        assert_eq!(result.class, "done");
+        let bkpt = result.results.get("bkpt").unwrap();
+        assert_eq!(bkpt.str("number"), Some("1"));
+        assert_eq!(bkpt.str("line"), Some("5"));
+        assert_eq!(bkpt.list("thread-groups").len(), 1);
+
+        let (token, result) = result_record("^running");
+        assert_eq!(token, None);
+        assert_eq!(result.class, "running");
+        assert!(matches!(result.results, MiValue::Tuple(fields) if fields.is_empty()));
+
+        let (_, result) = result_record(r#"3^error,msg="No symbol \"y\" in current context.""#);
+        assert_eq!(result.class, "error");
+        assert_eq!(
+            result.results.str("msg"),
+            Some(r#"No symbol "y" in current context."#)
+        );
+    }
+
+    #[test]
+    fn parses_async_records() {
+        let MiRecord::Async { class, results } = parse_record(
+            r#"*stopped,reason="breakpoint-hit",disp="keep",bkptno="1",frame={addr="0x0000555555555139",func="main",args=[],file="main.c",fullname="/tmp/main.c",line="5",arch="i386:x86-64"},thread-id="1",stopped-threads="all",core="3""#,
+        ) else {
+            panic!("not an async record");
+        };
+        assert_eq!(class, "stopped");
+        assert_eq!(results.str("reason"), Some("breakpoint-hit"));
+        assert_eq!(results.str("thread-id"), Some("1"));
+        let frame = results.get("frame").unwrap();
+        assert_eq!(frame.str("func"), Some("main"));
+        assert!(frame.list("args").is_empty());
+
+        let MiRecord::Async { class, results } =
+            parse_record(r#"=thread-group-started,id="i1",pid="4242""#)
+        else {
+            panic!("not an async record");
+        };
+        assert_eq!(class, "thread-group-started");
+        assert_eq!(results.str("pid"), Some("4242"));
+    }
+
+    #[test]
+    fn parses_lists_of_results_and_tuples() {
+        let (_, result) = result_record(
+            r#"^done,stack=[frame={level="0",addr="0x1139",func="inner",file="main.c",line="3"},frame={level="1",addr="0x1150",func="main",file="main.c",line="8"}]"#,
+        );
+        let stack = result.results.list("stack");
+        assert_eq!(stack.len(), 2);
+        assert_eq!(stack[0].str("func"), Some("inner"));
+        assert_eq!(stack[1].str("level"), Some("1"));
+
+        let (_, result) = result_record(
+            r#"^done,variables=[{name="x",type="int",value="1"},{name="s",type="char *",value="0x2004 \"hi\""}]"#,
+        );
+        let variables = result.results.list("variables");
+        assert_eq!(variables[0].str("name"), Some("x"));
+        assert_eq!(variables[1].str("value"), Some(r#"0x2004 "hi""#));
+    }
+
+    #[test]
+    fn decodes_stream_records() {
+        let MiRecord::Stream { kind, text } = parse_record(r#"~"caf\303\251\tok\n""#) else {
+            panic!("not a stream record");
+        };
+        assert_eq!(kind, '~');
+        assert_eq!(text, "café\tok\n");
+
+        assert!(matches!(
+            parse_record(r#"&"warning: \"x\"\n""#),
+            MiRecord::Stream { kind: '&', text } if text == "warning: \"x\"\n"
+        ));
+    }
+
+    #[test]
+    fn recognises_the_prompt() {
+        assert!(matches!(parse_record("(gdb) "), MiRecord::Prompt));
+        assert!(matches!(parse_record("(gdb)"), MiRecord::Prompt));
+    }
+
+    #[test]
+    fn treats_anything_else_as_program_output() {
+        assert!(is_other("hello world"));
+        assert!(is_other(""));
+        assert!(is_other("42"));
+        assert!(is_other(r#"^done,bkpt={number="1""#));
+        assert!(is_other(r#"~"unterminated"#));
+        assert!(is_other(r#"5~"tokens are not allowed on streams""#));
+    }
+
+    #[test]
+    fn quotes_mi_arguments() {
+        assert_eq!(mi_quote("main.c"), r#""main.c""#);
+        assert_eq!(mi_quote(r#"C:\src\"a".c"#), r#""C:\\src\\\"a\".c""#);
+    }
+}
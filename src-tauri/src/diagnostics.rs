@@ -0,0 +1,867 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Output format of a toolchain's compiler, used to pick a parser.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosticFormat {
+    /// GCC and Clang: `file:line:col: severity: message`.
+    Gcc,
+    /// rustc with `--error-format=json`, one JSON object per line.
+    Rustc,
+    /// go build: `file:line:col: message`, continuation lines indented.
+    Go,
+    /// tsc with `--pretty false`: `file(line,col): error TS1234: message`,
+    /// written to stdout.
+    Tsc,
+    /// cppcheck with the template `{file}:{line}:{column}: {severity}:
+    /// {message} [{id}]`.
+    Cppcheck,
+    /// pyflakes: `file:line:col: message`, written to stdout.
+    Pyflakes,
+    /// `go vet -json`: analyzer findings as JSON, type errors as plain
+    /// `go build` output.
+    GoVet,
+    /// eslint with `--format json`, written to stdout.
+    Eslint,
+}
+
+impl DiagnosticFormat {
+    /// Whether the compiler writes its diagnostics to stdout rather than
+    /// stderr.
+    pub fn uses_stdout(&self) -> bool {
+        matches!(
+            self,
+            DiagnosticFormat::Tsc | DiagnosticFormat::Pyflakes | DiagnosticFormat::Eslint
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+    Help,
+}
+
+/// One compiler or linter message. Locations are 1-based; `file` is
+/// relative to the build directory when it points into it. Messages without
+/// a location (linker errors, for one) leave those fields empty.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub file: Option<String>,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+    pub message: String,
+    pub notes: Vec<String>,
+    /// The rule or error code, as the tool names it: `-Wshadow`, `E0308`,
+    /// `clippy::needless_return`, `TS2304`, `no-unused-vars`, ...
+    #[serde(default)]
+    pub code: Option<String>,
+    /// The linter that reported it. Unset for compiler diagnostics.
+    #[serde(default)]
+    pub source: Option<String>,
+}
+
+impl Diagnostic {
+    fn new(severity: Severity, message: impl Into<String>) -> Self {
+        Self {
+            severity,
+            file: None,
+            line: None,
+            column: None,
+            message: message.into(),
+            notes: Vec::new(),
+            code: None,
+            source: None,
+        }
+    }
+
+    fn at(mut self, file: impl Into<String>, line: u32, column: Option<u32>) -> Self {
+        self.file = Some(file.into());
+        self.line = Some(line);
+        self.column = column;
+        self
+    }
+}
+
+/// Compiler output parsed into diagnostics, along with the human-readable
+/// text to show in the terminal.
+pub struct ParsedOutput {
+    pub diagnostics: Vec<Diagnostic>,
+    pub rendered: String,
+}
+
+pub fn parse(format: DiagnosticFormat, output: &str, build_dir: &Path) -> ParsedOutput {
+    let (mut diagnostics, rendered) = match format {
+        DiagnosticFormat::Gcc => (parse_gcc(output, build_dir), output.to_string()),
+        DiagnosticFormat::Rustc => parse_rustc(output),
+        DiagnosticFormat::Go => (parse_go(output), output.to_string()),
+        DiagnosticFormat::Tsc => (parse_tsc(output), output.to_string()),
+        DiagnosticFormat::Cppcheck => (parse_cppcheck(output), output.to_string()),
+        DiagnosticFormat::Pyflakes => (parse_pyflakes(output), output.to_string()),
+        DiagnosticFormat::GoVet => (parse_go_vet(output), output.to_string()),
+        DiagnosticFormat::Eslint => (parse_eslint(output), output.to_string()),
+    };
+
+    for diagnostic in &mut diagnostics {
+        if let Some(file) = &diagnostic.file {
+            diagnostic.file = Some(relative_to(build_dir, file));
+        }
+    }
+
+    ParsedOutput {
+        diagnostics,
+        rendered,
+    }
+}
+
+fn relative_to(base: &Path, file: &str) -> String {
+    let path = Path::new(file);
+    let relative = path.strip_prefix(base).unwrap_or(path);
+    let relative = relative.strip_prefix(".").unwrap_or(relative);
+    relative.to_string_lossy().into_owned()
+}
+
+/// Splits `file:line[:col]` into its parts. The file may itself contain
+/// colons (Windows drive letters), so the numbers are taken from the right.
+pub(crate) fn parse_location(location: &str) -> Option<(String, u32, Option<u32>)> {
+    let mut parts = location.rsplitn(3, ':');
+    let last = parts.next()?.trim().parse::<u32>().ok()?;
+    let middle = parts.next()?;
+
+    match (middle.trim().parse::<u32>(), parts.next()) {
+        (Ok(line), Some(file)) if !file.is_empty() => Some((file.to_string(), line, Some(last))),
+        _ if !middle.is_empty() => {
+            // No column; `middle` and anything before it make up the file
+            let file = location.rsplit_once(':')?.0;
+            Some((file.to_string(), last, None))
+        }
+        _ => None,
+    }
+}
+
+const GCC_SEVERITIES: [(&str, Severity); 5] = [
+    (": fatal error: ", Severity::Error),
+    (": error: ", Severity::Error),
+    (": warning: ", Severity::Warning),
+    (": note: ", Severity::Note),
+    (": remark: ", Severity::Note),
+];
+
+fn parse_gcc(output: &str, build_dir: &Path) -> Vec<Diagnostic> {
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+
+    for line in output.lines() {
+        // Padded so messages without a location ("error: linker command
+        // failed") match the same markers
+        let padded = format!(": {}", line);
+        let Some((index, marker, severity)) = GCC_SEVERITIES
+            .iter()
+            .filter_map(|(marker, severity)| padded.find(marker).map(|i| (i, *marker, *severity)))
+            .min_by_key(|(i, _, _)| *i)
+        else {
+            continue;
+        };
+
+        let message = padded[index + marker.len()..].trim();
+        let location = padded.get(2..index).and_then(parse_location);
+        // Warnings end with the flag that enables them: `[-Wshadow]`
+        let (message, code) = match message.rsplit_once(" [") {
+            Some((text, flag)) if flag.starts_with("-W") && flag.ends_with(']') => (
+                text.to_string(),
+                Some(flag.trim_end_matches(']').to_string()),
+            ),
+            _ => (message.to_string(), None),
+        };
+
+        // Notes elaborate on the diagnostic before them
+        if severity == Severity::Note {
+            if let Some(last) = diagnostics.last_mut() {
+                last.notes.push(match &location {
+                    Some((file, line, _)) => {
+                        format!("{}:{}: {}", relative_to(build_dir, file), line, message)
+                    }
+                    None => message,
+                });
+                continue;
+            }
+        }
+
+        let mut diagnostic = Diagnostic::new(severity, message);
+        if let Some((file, line, column)) = location {
+            diagnostic = diagnostic.at(file, line, column);
+        }
+        diagnostic.code = code;
+        diagnostics.push(diagnostic);
+    }
+
+    diagnostics
+}
+
+fn parse_go(output: &str) -> Vec<Diagnostic> {
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+
+    for line in output.lines() {
+        if line.starts_with('\t') || line.starts_with("    ") {
+            if let Some(last) = diagnostics.last_mut() {
+                last.notes.push(line.trim().to_string());
+            }
+            continue;
+        }
+
+        let Some((location, message)) = line.split_once(": ") else {
+            continue;
+        };
+        let Some((file, line, column)) = parse_location(location) else {
+            continue;
+        };
+
+        diagnostics.push(Diagnostic::new(Severity::Error, message.trim()).at(file, line, column));
+    }
+
+    diagnostics
+}
+
+fn parse_tsc(output: &str) -> Vec<Diagnostic> {
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+
+    for line in output.lines() {
+        // Message chains continue on indented lines
+        if line.starts_with(' ') {
+            if let Some(last) = diagnostics.last_mut() {
+                last.notes.push(line.trim().to_string());
+            }
+            continue;
+        }
+
+        let Some((location, rest)) = line.split_once("): ") else {
+            continue;
+        };
+        let Some((file, position)) = location.rsplit_once('(') else {
+            continue;
+        };
+        let Some((line, column)) = position.split_once(',') else {
+            continue;
+        };
+        let (Ok(line), Ok(column)) = (line.parse::<u32>(), column.parse::<u32>()) else {
+            continue;
+        };
+
+        let (severity, message) = if let Some(message) = rest.strip_prefix("error ") {
+            (Severity::Error, message)
+        } else if let Some(message) = rest.strip_prefix("warning ") {
+            (Severity::Warning, message)
+        } else {
+            continue;
+        };
+
+        // `TS2304: Cannot find name 'x'.`
+        let (code, message) = match message.split_once(": ") {
+            Some((code, text)) if code.starts_with("TS") => (Some(code.to_string()), text),
+            _ => (None, message),
+        };
+        let mut diagnostic = Diagnostic::new(severity, message).at(file, line, Some(column));
+        diagnostic.code = code;
+        diagnostics.push(diagnostic);
+    }
+
+    diagnostics
+}
+
+fn parse_cppcheck(output: &str) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    for line in output.lines() {
+        // `main.c:4:9: style: Variable 'x' is assigned a value that is never used. [unreadVariable]`
+        let Some((rest, id)) = line
+            .trim_end()
+            .strip_suffix(']')
+            .and_then(|l| l.rsplit_once(" ["))
+        else {
+            continue;
+        };
+        let Some((location, rest)) = rest.split_once(": ") else {
+            continue;
+        };
+        let Some((severity, message)) = rest.split_once(": ") else {
+            continue;
+        };
+        // A summary of the checks that ran, not a finding
+        if id == "checkersReport" {
+            continue;
+        }
+        let severity = match severity {
+            "error" => Severity::Error,
+            "warning" | "style" | "performance" | "portability" => Severity::Warning,
+            "information" => Severity::Note,
+            _ => continue,
+        };
+
+        let mut diagnostic = Diagnostic::new(severity, message);
+        // Some findings (missing headers, for one) aren't tied to a line
+        if let Some((file, line, column)) =
+            parse_location(location).filter(|(_, line, _)| *line > 0)
+        {
+            diagnostic = diagnostic.at(file, line, column);
+        }
+        diagnostic.code = Some(id.to_string());
+        diagnostics.push(diagnostic);
+    }
+
+    diagnostics
+}
+
+/// pyflakes has no rule ids of its own; its messages get flake8's codes
+/// for them.
+const PYFLAKES_CODES: [(&str, &str, Severity); 9] = [
+    ("imported but unused", "F401", Severity::Warning),
+    (
+        "may be undefined, or defined from star imports",
+        "F405",
+        Severity::Warning,
+    ),
+    (
+        "used; unable to detect undefined names",
+        "F403",
+        Severity::Warning,
+    ),
+    ("redefinition of unused", "F811", Severity::Warning),
+    ("undefined name", "F821", Severity::Error),
+    ("referenced before assignment", "F823", Severity::Error),
+    ("is assigned to but never used", "F841", Severity::Warning),
+    (
+        "f-string is missing placeholders",
+        "F541",
+        Severity::Warning,
+    ),
+    ("'return' outside function", "F706", Severity::Error),
+];
+
+fn parse_pyflakes(output: &str) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut lines = output.lines().peekable();
+
+    while let Some(line) = lines.next() {
+        if line.starts_with(' ') || line.trim().is_empty() {
+            continue;
+        }
+        // Columns are 1-based since pyflakes 2.2; older versions leave them out
+        let Some((location, message)) = line.split_once(": ") else {
+            continue;
+        };
+        let Some((file, line, column)) = parse_location(location) else {
+            continue;
+        };
+
+        let known = PYFLAKES_CODES
+            .iter()
+            .find(|(text, _, _)| message.contains(text));
+        // Syntax errors are followed by the offending line and a caret
+        let syntax_error = lines.peek().is_some_and(|next| next.starts_with(' '));
+        let severity = match known {
+            Some((_, _, severity)) => *severity,
+            None if syntax_error => Severity::Error,
+            None => Severity::Warning,
+        };
+        let mut diagnostic = Diagnostic::new(severity, message.trim()).at(file, line, column);
+        diagnostic.code = known.map(|(_, code, _)| code.to_string());
+        diagnostics.push(diagnostic);
+    }
+
+    diagnostics
+}
+
+#[derive(Deserialize)]
+struct GoVetFinding {
+    posn: String,
+    message: String,
+}
+
+/// Package to analyzer to findings. Analyzers that fail report an `error`
+/// object instead of a list, which is skipped.
+type GoVetOutput =
+    std::collections::BTreeMap<String, std::collections::BTreeMap<String, serde_json::Value>>;
+
+fn parse_go_vet(output: &str) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut plain = String::new();
+    let mut json = String::new();
+
+    // JSON objects start and end at column 0; everything else, other than
+    // the `# package` headers, is type errors in `go build`'s format
+    let mut in_json = false;
+    for line in output.lines() {
+        // The vet tool's own type errors come with its log prefix
+        let line = line.strip_prefix("vet: ").unwrap_or(line);
+        if line == "{" {
+            in_json = true;
+        }
+        if in_json {
+            json.push_str(line);
+            json.push('\n');
+            in_json = line != "}";
+        } else if !line.starts_with('#') {
+            plain.push_str(line);
+            plain.push('\n');
+        }
+    }
+
+    let packages = serde_json::Deserializer::from_str(&json).into_iter::<GoVetOutput>();
+    for analyzers in packages.flatten().flat_map(|p| p.into_values()) {
+        for (analyzer, findings) in analyzers {
+            let Ok(findings) = serde_json::from_value::<Vec<GoVetFinding>>(findings) else {
+                continue;
+            };
+            for finding in findings {
+                let mut diagnostic = Diagnostic::new(Severity::Warning, finding.message);
+                if let Some((file, line, column)) = parse_location(&finding.posn) {
+                    diagnostic = diagnostic.at(file, line, column);
+                }
+                diagnostic.code = Some(analyzer.clone());
+                diagnostics.push(diagnostic);
+            }
+        }
+    }
+
+    diagnostics.extend(parse_go(&plain));
+    diagnostics
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EslintFile {
+    file_path: String,
+    messages: Vec<EslintMessage>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EslintMessage {
+    rule_id: Option<String>,
+    /// 1 for warnings, 2 for errors.
+    severity: u8,
+    message: String,
+    line: Option<u32>,
+    column: Option<u32>,
+}
+
+fn parse_eslint(output: &str) -> Vec<Diagnostic> {
+    let Ok(files) = serde_json::from_str::<Vec<EslintFile>>(output.trim()) else {
+        return Vec::new();
+    };
+
+    let mut diagnostics = Vec::new();
+    for file in files {
+        for message in file.messages {
+            let severity = match message.severity {
+                2 => Severity::Error,
+                _ => Severity::Warning,
+            };
+            let mut diagnostic = Diagnostic::new(severity, message.message);
+            if let Some(line) = message.line {
+                diagnostic = diagnostic.at(file.file_path.clone(), line, message.column);
+            }
+            diagnostic.code = message.rule_id;
+            diagnostics.push(diagnostic);
+        }
+    }
+    diagnostics
+}
+
+#[derive(Deserialize)]
+struct RustcDiagnostic {
+    message: String,
+    level: String,
+    #[serde(default)]
+    spans: Vec<RustcSpan>,
+    #[serde(default)]
+    children: Vec<RustcDiagnostic>,
+    rendered: Option<String>,
+    code: Option<RustcCode>,
+}
+
+#[derive(Deserialize)]
+struct RustcCode {
+    code: String,
+}
+
+#[derive(Deserialize)]
+struct RustcSpan {
+    file_name: String,
+    line_start: u32,
+    column_start: u32,
+    is_primary: bool,
+}
+
+/// Parses rustc's JSON output. Lines that aren't JSON (rustc can still
+/// print plain text, e.g. for ICEs) are kept in the rendered text as is.
+fn parse_rustc(output: &str) -> (Vec<Diagnostic>, String) {
+    let mut diagnostics = Vec::new();
+    let mut rendered = String::new();
+
+    for line in output.lines() {
+        let Ok(diagnostic) = serde_json::from_str::<RustcDiagnostic>(line) else {
+            rendered.push_str(line);
+            rendered.push('\n');
+            continue;
+        };
+
+        if let Some(text) = &diagnostic.rendered {
+            rendered.push_str(text);
+        }
+
+        // "aborting due to N previous errors", "N warnings emitted" and the
+        // `--explain` hint carry no location and add nothing to the
+        // diagnostics themselves
+        if diagnostic.spans.is_empty()
+            && (diagnostic.level == "failure-note"
+                || diagnostic.message.starts_with("aborting due to")
+                || diagnostic.message.ends_with("emitted"))
+        {
+            continue;
+        }
+
+        let primary = diagnostic
+            .spans
+            .iter()
+            .find(|span| span.is_primary)
+            .or(diagnostic.spans.first());
+        let notes = diagnostic
+            .children
+            .iter()
+            .map(|child| format!("{}: {}", child.level, child.message))
+            .collect();
+
+        diagnostics.push(Diagnostic {
+            severity: rustc_severity(&diagnostic.level),
+            file: primary.map(|span| span.file_name.clone()),
+            line: primary.map(|span| span.line_start),
+            column: primary.map(|span| span.column_start),
+            message: diagnostic.message,
+            notes,
+            code: diagnostic.code.map(|code| code.code),
+            source: None,
+        });
+    }
+
+    (diagnostics, rendered)
+}
+
+fn rustc_severity(level: &str) -> Severity {
+    match level {
+        "warning" => Severity::Warning,
+        "note" | "failure-note" => Severity::Note,
+        "help" => Severity::Help,
+        _ => Severity::Error,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BUILD_DIR: &str = "/tmp/builds/run-1";
+
+    fn parse_with(format: DiagnosticFormat, output: &str) -> Vec<Diagnostic> {
+        parse(format, output, Path::new(BUILD_DIR)).diagnostics
+    }
+
+    fn location(diagnostic: &Diagnostic) -> (Option<&str>, Option<u32>, Option<u32>) {
+        (
+            diagnostic.file.as_deref(),
+            diagnostic.line,
+            diagnostic.column,
+        )
+    }
+
+    #[test]
+    fn location_with_and_without_column() {
+        assert_eq!(
+            parse_location("main.c:12:5"),
+            Some(("main.c".to_string(), 12, Some(5)))
+        );
+        assert_eq!(
+            parse_location("main.py:7"),
+            Some(("main.py".to_string(), 7, None))
+        );
+        assert_eq!(
+            parse_location(r"C:\Users\me\main.c:3:14"),
+            Some((r"C:\Users\me\main.c".to_string(), 3, Some(14)))
+        );
+        assert_eq!(
+            parse_location(r"C:\Users\me\main.c:3"),
+            Some((r"C:\Users\me\main.c".to_string(), 3, None))
+        );
+        assert_eq!(parse_location("collect2"), None);
+        assert_eq!(parse_location(":4"), None);
+    }
+
+    #[test]
+    fn gcc_errors_warnings_and_notes() {
+        let output = "\
+/tmp/builds/run-1/main.c: In function 'main':
+/tmp/builds/run-1/main.c:6:20: error: too few arguments to function 'add'
+    6 |     printf(\"%d\\n\", add(1));
+      |                    ^~~
+/tmp/builds/run-1/main.c:2:5: note: declared here
+    2 | int add(int a, int b) { return a + b; }
+      |     ^~~
+/tmp/builds/run-1/main.c:7:12: error: 'undefined_var' undeclared (first use in this function)
+/tmp/builds/run-1/main.c:7:12: note: each undeclared identifier is reported only once for each function it appears in
+/tmp/builds/run-1/main.c:5:9: warning: unused variable 'y' [-Wunused-variable]
+    5 |     int y;
+      |         ^
+";
+        let diagnostics = parse_with(DiagnosticFormat::Gcc, output);
+
+        assert_eq!(diagnostics.len(), 3);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert_eq!(
+            diagnostics[0].message,
+            "too few arguments to function 'add'"
+        );
+        assert_eq!(
+            location(&diagnostics[0]),
+            (Some("main.c"), Some(6), Some(20))
+        );
+        assert_eq!(diagnostics[0].notes, ["main.c:2: declared here"]);
+        assert_eq!(diagnostics[1].notes.len(), 1);
+        assert_eq!(diagnostics[2].severity, Severity::Warning);
+        assert_eq!(diagnostics[2].message, "unused variable 'y'");
+        assert_eq!(diagnostics[2].code.as_deref(), Some("-Wunused-variable"));
+    }
+
+    #[test]
+    fn gcc_messages_without_location() {
+        let output = "\
+/usr/bin/ld: /tmp/ccXo1a2b.o: in function `main':
+main.c:(.text+0x13): undefined reference to `missing'
+collect2: error: ld returned 1 exit status
+cc1: fatal error: main.c: No such file or directory
+";
+        let diagnostics = parse_with(DiagnosticFormat::Gcc, output);
+
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].message, "ld returned 1 exit status");
+        assert_eq!(location(&diagnostics[0]), (None, None, None));
+        assert_eq!(diagnostics[1].message, "main.c: No such file or directory");
+        assert_eq!(diagnostics[1].severity, Severity::Error);
+    }
+
+    #[test]
+    fn gcc_windows_paths() {
+        let output = r"C:\Users\me\main.c:4:9: warning: unused variable 'x' [-Wunused-variable]
+C:\Users\me\main.c:9: error: expected ';' before '}' token
+";
+        let diagnostics = parse_with(DiagnosticFormat::Gcc, output);
+
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(
+            location(&diagnostics[0]),
+            (Some(r"C:\Users\me\main.c"), Some(4), Some(9))
+        );
+        assert_eq!(
+            location(&diagnostics[1]),
+            (Some(r"C:\Users\me\main.c"), Some(9), None)
+        );
+    }
+
+    #[test]
+    fn rustc_json() {
+        let output = concat!(
+            r#"{"$message_type":"diagnostic","message":"mismatched types","code":{"code":"E0308","explanation":null},"level":"error","spans":[{"file_name":"/tmp/builds/run-1/main.rs","byte_start":29,"byte_end":36,"line_start":2,"line_end":2,"column_start":18,"column_end":25,"is_primary":true,"text":[],"label":"expected `i32`, found `&str`","suggested_replacement":null,"suggestion_applicability":null,"expansion":null},{"file_name":"/tmp/builds/run-1/main.rs","byte_start":23,"byte_end":26,"line_start":2,"line_end":2,"column_start":12,"column_end":15,"is_primary":false,"text":[],"label":"expected due to this","suggested_replacement":null,"suggestion_applicability":null,"expansion":null}],"children":[],"rendered":"error[E0308]: mismatched types\n --> main.rs:2:18\n\n"}"#,
+            "\n",
+            r#"{"$message_type":"diagnostic","message":"unused variable: `unused`","code":{"code":"unused_variables","explanation":null},"level":"warning","spans":[{"file_name":"/tmp/builds/run-1/main.rs","byte_start":20,"byte_end":26,"line_start":3,"line_end":3,"column_start":9,"column_end":15,"is_primary":true,"text":[],"label":null,"suggested_replacement":null,"suggestion_applicability":null,"expansion":null}],"children":[{"message":"`#[warn(unused_variables)]` on by default","code":null,"level":"note","spans":[],"children":[],"rendered":null},{"message":"if this is intentional, prefix it with an underscore","code":null,"level":"help","spans":[],"children":[],"rendered":null}],"rendered":"warning: unused variable: `unused`\n\n"}"#,
+            "\n",
+            r#"{"$message_type":"diagnostic","message":"aborting due to 1 previous error; 1 warning emitted","code":null,"level":"error","spans":[],"children":[],"rendered":"error: aborting due to 1 previous error; 1 warning emitted\n\n"}"#,
+            "\n",
+            r#"{"$message_type":"diagnostic","message":"For more information about this error, try `rustc --explain E0308`.","code":null,"level":"failure-note","spans":[],"children":[],"rendered":"For more information about this error, try `rustc --explain E0308`.\n"}"#,
+            "\n",
+            "thread 'rustc' panicked at compiler/rustc_middle/src/ty/mod.rs:1:1\n",
+        );
+        let parsed = parse(DiagnosticFormat::Rustc, output, Path::new(BUILD_DIR));
+        let diagnostics = parsed.diagnostics;
+
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert_eq!(diagnostics[0].code.as_deref(), Some("E0308"));
+        // The primary span, not the first one
+        assert_eq!(
+            location(&diagnostics[0]),
+            (Some("main.rs"), Some(2), Some(18))
+        );
+        assert_eq!(diagnostics[1].severity, Severity::Warning);
+        assert_eq!(
+            diagnostics[1].notes,
+            [
+                "note: `#[warn(unused_variables)]` on by default",
+                "help: if this is intentional, prefix it with an underscore",
+            ]
+        );
+
+        assert!(parsed
+            .rendered
+            .starts_with("error[E0308]: mismatched types\n"));
+        assert!(parsed.rendered.contains("error: aborting due to"));
+        assert!(parsed
+            .rendered
+            .ends_with("thread 'rustc' panicked at compiler/rustc_middle/src/ty/mod.rs:1:1\n"));
+    }
+
+    #[test]
+    fn go_errors_with_continuation_lines() {
+        let output = "\
+# command-line-arguments
+./main.go:5:2: declared and not used: x
+./main.go:9:12: not enough arguments in call to add
+\thave (number)
+\twant (int, int)
+";
+        let diagnostics = parse_with(DiagnosticFormat::Go, output);
+
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].message, "declared and not used: x");
+        assert_eq!(
+            location(&diagnostics[0]),
+            (Some("main.go"), Some(5), Some(2))
+        );
+        assert_eq!(diagnostics[1].notes, ["have (number)", "want (int, int)"]);
+    }
+
+    #[test]
+    fn tsc_errors_and_message_chains() {
+        let output = "\
+main.ts(3,7): error TS2322: Type 'string' is not assignable to type 'number'.
+main.ts(8,5): error TS2345: Argument of type '{ a: string; }' is not assignable to parameter of type 'Opts'.
+  Types of property 'a' are incompatible.
+    Type 'string' is not assignable to type 'number'.
+C:\\proj\\main.ts(1,1): warning TS6133: 'fs' is declared but its value is never read.
+";
+        let diagnostics = parse_with(DiagnosticFormat::Tsc, output);
+
+        assert_eq!(diagnostics.len(), 3);
+        assert_eq!(diagnostics[0].code.as_deref(), Some("TS2322"));
+        assert_eq!(
+            diagnostics[0].message,
+            "Type 'string' is not assignable to type 'number'."
+        );
+        assert_eq!(
+            location(&diagnostics[0]),
+            (Some("main.ts"), Some(3), Some(7))
+        );
+        assert_eq!(diagnostics[1].notes.len(), 2);
+        assert_eq!(diagnostics[2].severity, Severity::Warning);
+        assert_eq!(
+            location(&diagnostics[2]),
+            (Some(r"C:\proj\main.ts"), Some(1), Some(1))
+        );
+    }
+
+    #[test]
+    fn cppcheck_findings() {
+        let output = "\
+main.c:4:9: style: Variable 'x' is assigned a value that is never used. [unreadVariable]
+main.c:7:12: error: Array 'a[3]' accessed at index 3, which is out of bounds. [arrayIndexOutOfBounds]
+main.c:0:0: information: Include file: <stdio.h> not found. Please note: Cppcheck does not need standard library headers to get proper results. [missingIncludeSystem]
+nofile:0:0: information: Active checkers: 106/592 (use --checkers-report=<filename> to see details) [checkersReport]
+";
+        let diagnostics = parse_with(DiagnosticFormat::Cppcheck, output);
+
+        assert_eq!(diagnostics.len(), 3);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(diagnostics[0].code.as_deref(), Some("unreadVariable"));
+        assert_eq!(
+            location(&diagnostics[0]),
+            (Some("main.c"), Some(4), Some(9))
+        );
+        assert_eq!(diagnostics[1].severity, Severity::Error);
+        assert_eq!(diagnostics[2].severity, Severity::Note);
+        assert!(diagnostics[2]
+            .message
+            .starts_with("Include file: <stdio.h> not found."));
+        assert_eq!(location(&diagnostics[2]), (None, None, None));
+    }
+
+    #[test]
+    fn pyflakes_messages() {
+        let output = "\
+main.py:1:1: 'os' imported but unused
+main.py:4:5: local variable 'x' is assigned to but never used
+main.py:6: undefined name 'y'
+main.py:9:11: '(' was never closed
+    print(\"a\"
+          ^
+";
+        let diagnostics = parse_with(DiagnosticFormat::Pyflakes, output);
+
+        assert_eq!(diagnostics.len(), 4);
+        assert_eq!(diagnostics[0].code.as_deref(), Some("F401"));
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(diagnostics[1].code.as_deref(), Some("F841"));
+        // Older pyflakes leave the column out
+        assert_eq!(location(&diagnostics[2]), (Some("main.py"), Some(6), None));
+        assert_eq!(diagnostics[2].severity, Severity::Error);
+        assert_eq!(diagnostics[3].severity, Severity::Error);
+        assert_eq!(diagnostics[3].code, None);
+    }
+
+    #[test]
+    fn go_vet_json_and_type_errors() {
+        let output = "\
+# command-line-arguments
+{
+\t\"command-line-arguments\": {
+\t\t\"printf\": [
+\t\t\t{
+\t\t\t\t\"posn\": \"/tmp/builds/run-1/main.go:6:2\",
+\t\t\t\t\"message\": \"fmt.Printf format %d has arg s of wrong type string\"
+\t\t\t}
+\t\t],
+\t\t\"buildtag\": {
+\t\t\t\"error\": \"analysis skipped\"
+\t\t}
+\t}
+}
+vet: ./main.go:9:2: declared and not used: y
+";
+        let diagnostics = parse_with(DiagnosticFormat::GoVet, output);
+
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].code.as_deref(), Some("printf"));
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(
+            location(&diagnostics[0]),
+            (Some("main.go"), Some(6), Some(2))
+        );
+        assert_eq!(diagnostics[1].severity, Severity::Error);
+        assert_eq!(diagnostics[1].message, "declared and not used: y");
+        assert_eq!(
+            location(&diagnostics[1]),
+            (Some("main.go"), Some(9), Some(2))
+        );
+    }
+
+    #[test]
+    fn eslint_json() {
+        let output = r#"[{"filePath":"/tmp/builds/run-1/main.js","messages":[{"ruleId":"no-unused-vars","severity":2,"message":"'x' is assigned a value but never used.","line":1,"column":7,"nodeType":"Identifier","messageId":"unusedVar","endLine":1,"endColumn":8},{"ruleId":"prefer-const","severity":1,"message":"'y' is never reassigned. Use 'const' instead.","line":2,"column":5},{"ruleId":null,"severity":1,"message":"File ignored because of a matching ignore pattern."}],"suppressedMessages":[],"errorCount":1,"fatalErrorCount":0,"warningCount":2,"fixableErrorCount":0,"fixableWarningCount":1}]
+"#;
+        let diagnostics = parse_with(DiagnosticFormat::Eslint, output);
+
+        assert_eq!(diagnostics.len(), 3);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert_eq!(diagnostics[0].code.as_deref(), Some("no-unused-vars"));
+        assert_eq!(
+            location(&diagnostics[0]),
+            (Some("main.js"), Some(1), Some(7))
+        );
+        assert_eq!(diagnostics[1].severity, Severity::Warning);
+        assert_eq!(location(&diagnostics[2]), (None, None, None));
+        assert_eq!(diagnostics[2].code, None);
+
+        assert!(parse_with(DiagnosticFormat::Eslint, "Oops! Something went wrong!").is_empty());
+    }
+}
@@ -1,176 +1,1131 @@
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
 use std::io::Write;
-#[cfg(target_os = "windows")]
-use std::os::windows::process::CommandExt;
-use std::process::Command;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::process::ChildStdin;
+
+use crate::compile_cache::{CacheSlot, CompileCache};
+use crate::coverage::{Coverage, CoverageReport};
+use crate::diagnostics::{self, Diagnostic, DiagnosticFormat};
+use crate::limits::ResourceLimits;
+use crate::process::{self, Process, ProcessExit};
+use crate::projects::{self, ProjectKind};
+use crate::run_config::{self, RunConfig};
+use crate::sandbox::{ExecutionMode, Sandbox};
+use crate::sanitizers::{self, SanitizerFinding};
+use crate::settings;
+use crate::shell::{self, ShellSessions};
+use crate::toolchains::{self, BuildPaths, BuildProfile};
+use crate::workspace::BuildWorkspaces;
+
+static NEXT_RUN_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Output kept per stream for the final `ExecutionResult`; anything past it
+/// is still streamed, just not retained.
+const MAX_CAPTURE_BYTES: usize = 1024 * 1024;
+
+/// Finished runs whose results `execution_status` can still return; older
+/// ones are forgotten.
+const MAX_FINISHED_RUNS: usize = 32;
+
+#[derive(Clone, Serialize)]
+pub struct OutputChunk {
+    run_id: u64,
+    data: String,
+}
+
+#[derive(Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    Compile,
+    Run,
+}
+
+/// Outcome of an `execute_code` run, emitted as `execution-exit`. `phase` is
+/// the phase the run ended in, and the exit status and output belong to it:
+/// a failed build reports the compiler's, anything else the program's.
+#[derive(Clone, Serialize)]
+pub struct ExecutionResult {
+    pub run_id: u64,
+    pub phase: Phase,
+    /// The program ran and exited with status 0.
+    pub success: bool,
+    pub exit_code: Option<i32>,
+    /// Signal that terminated the process (Unix only).
+    pub signal: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+    pub compile_time_ms: Option<u64>,
+    pub run_time_ms: Option<u64>,
+    /// `run_time_ms` at full precision, for benchmarks.
+    #[serde(skip)]
+    pub run_elapsed: Option<Duration>,
+    /// Peak resident memory of the program in KiB (Unix only).
+    pub peak_memory_kb: Option<u64>,
+    pub reason: Option<StopReason>,
+    /// Set when a process could not be started at all.
+    pub error: Option<String>,
+    /// Errors and warnings parsed from the compiler's output, for toolchains
+    /// that declare a diagnostic format.
+    pub diagnostics: Vec<Diagnostic>,
+    /// Build system used when the project was built as a whole rather than
+    /// as a single file.
+    pub project: Option<ProjectKind>,
+    /// Says so when the project was run instead of the code that was sent.
+    pub note: Option<String>,
+    /// The compiler didn't run: the binary of an identical earlier build
+    /// came from the compile cache.
+    pub cached_build: bool,
+    /// Sanitizer reports and Rust check failures in the program's stderr.
+    pub sanitizer_findings: Vec<SanitizerFinding>,
+    /// Lines and branches that ran, for runs with coverage on.
+    pub coverage: Option<CoverageReport>,
+}
+
+impl ExecutionResult {
+    fn new(run_id: u64, phase: Phase) -> Self {
+        Self {
+            run_id,
+            phase,
+            success: false,
+            exit_code: None,
+            signal: None,
+            stdout: String::new(),
+            stderr: String::new(),
+            compile_time_ms: None,
+            run_time_ms: None,
+            run_elapsed: None,
+            peak_memory_kb: None,
+            reason: None,
+            error: None,
+            diagnostics: Vec::new(),
+            project: None,
+            note: None,
+            cached_build: false,
+            sanitizer_findings: Vec::new(),
+            coverage: None,
+        }
+    }
+
+    fn record(&mut self, outcome: PhaseOutcome) {
+        self.exit_code = outcome.exit.status.code();
+        self.signal = process::exit_signal(&outcome.exit.status);
+        self.stdout = outcome.stdout;
+        self.stderr = outcome.stderr;
+    }
+}
+
+/// What one spawned process (compiler or program) produced.
+struct PhaseOutcome {
+    exit: ProcessExit,
+    stdout: String,
+    stderr: String,
+    elapsed: Duration,
+}
+
+/// Why a run was stopped before it exited on its own.
+#[derive(Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StopReason {
+    Timeout,
+    Killed,
+    CpuLimit,
+    MemoryLimit,
+    FileSizeLimit,
+}
+
+impl StopReason {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            StopReason::Timeout => "timed out",
+            StopReason::Killed => "killed",
+            StopReason::CpuLimit => "CPU time limit exceeded",
+            StopReason::MemoryLimit => "memory limit exceeded",
+            StopReason::FileSizeLimit => "file size limit exceeded",
+        }
+    }
+}
+
+/// The build step of a compiled language or project.
+struct CompileStep {
+    /// Commands run in order until one fails.
+    commands: Vec<Vec<String>>,
+    /// The run's build directory for single files, the project root for
+    /// project builds.
+    dir: PathBuf,
+    diagnostics: Option<DiagnosticFormat>,
+    /// Set for single-file builds when the compile cache is enabled.
+    cache: Option<CacheSlot>,
+    /// Project builds run the project's own code (build scripts, Makefile
+    /// recipes), so they are supervised like the run phase.
+    supervised: bool,
+}
+
+/// The program to run, with its run configuration applied.
+#[derive(Clone)]
+struct RunStep {
+    argv: Vec<String>,
+    env: Vec<(String, String)>,
+    dir: PathBuf,
+    /// Fixed stdin from the run configuration.
+    stdin: Option<Vec<u8>>,
+}
+
+/// Which output streams are forwarded to the frontend as they arrive. The
+/// output is captured either way.
+#[derive(Clone, Copy)]
+pub(crate) struct Echo {
+    stdout: bool,
+    stderr: bool,
+}
+
+impl Echo {
+    const ALL: Echo = Echo {
+        stdout: true,
+        stderr: true,
+    };
+    const NONE: Echo = Echo {
+        stdout: false,
+        stderr: false,
+    };
+}
+
+/// How the run phase of a program, and a project's build, is supervised.
+/// Compilers of single files run without it.
+struct Supervision {
+    timeout: Option<Duration>,
+    limits: ResourceLimits,
+    sandbox: Option<Sandbox>,
+}
 
 #[tauri::command]
 pub async fn execute_code(
+    app: AppHandle,
     language: String,
     code: String,
+    config: Option<RunConfig>,
+    config_name: Option<String>,
     state: State<'_, AppState>,
-) -> Result<String, String> {
+    registry: State<'_, RunRegistry>,
+) -> Result<u64, String> {
     let cwd = state.cwd.lock().map_err(|e| e.to_string())?.clone();
-    let ext: &str;
-    let cmd: &str;
-    let mut args: Vec<&str> = vec![];
+    let config = match (config, config_name) {
+        (Some(config), _) => config,
+        (None, Some(name)) => run_config::find(&cwd, &name)?,
+        (None, None) => RunConfig::default(),
+    };
 
-    // Simple routing based on language
-    match language.to_lowercase().as_str() {
-        "python" => {
-            ext = "py";
-            cmd = "python";
+    // 1. Write the code out and work out the build and run commands
+    let prepared = PreparedRun::new(&app, &cwd, &language, &code, config)?;
+    let run_id = prepared.run_id;
+    registry.insert(run_id, prepared.initial_status());
+
+    // 2. Execute in the background; output arrives as execution-* events
+    tauri::async_runtime::spawn(async move {
+        let mut result = run_phases(&app, &prepared).await;
+        result.project = prepared.project;
+        result.note = prepared.project.map(|kind| {
+            format!(
+                "Ran the project described by {}; the code in the editor was not run",
+                kind.marker()
+            )
+        });
+        prepared.release(&app);
+
+        // 3. Report how the run ended
+        app.state::<RunRegistry>()
+            .set_status(run_id, RunStatus::Finished(Box::new(result.clone())));
+        let _ = app.emit("execution-exit", result);
+    });
+
+    Ok(run_id)
+}
+
+/// A run whose code has been written out and whose build and run commands
+/// have been resolved, so configuration errors surface before anything is
+/// spawned.
+pub(crate) struct PreparedRun {
+    pub run_id: u64,
+    compile: Option<CompileStep>,
+    run: RunStep,
+    supervision: Supervision,
+    build_dir: PathBuf,
+    retain_builds: usize,
+    project: Option<ProjectKind>,
+    /// The written-out code, for single files.
+    source: Option<PathBuf>,
+    coverage: Option<Coverage>,
+}
+
+impl PreparedRun {
+    pub fn new(
+        app: &AppHandle,
+        cwd: &Path,
+        language: &str,
+        code: &str,
+        config: RunConfig,
+    ) -> Result<Self, String> {
+        let settings = settings::load(cwd)?;
+        let run_dir = config.working_dir(cwd)?;
+        let stdin = config.stdin_bytes(cwd)?;
+        let toolchains = toolchains::load(app, cwd)?;
+        let toolchain = toolchains
+            .find(language)
+            .ok_or_else(|| format!("Unsupported language for execution: {}", language))?;
+
+        let (profile, extra_flags) =
+            toolchain.select_profile(config.profile, settings.execution.build_profile)?;
+
+        if config.coverage && settings.execution.mode == ExecutionMode::Sandbox {
+            // The counts are written to the build directory, which the
+            // sandbox keeps read-only
+            return Err("Coverage is not available in sandbox mode".to_string());
         }
-        "javascript" | "typescript" => {
-            ext = "js";
-            cmd = "node";
+
+        let mut limits = settings.limits;
+        if profile == BuildProfile::Sanitize {
+            // ASan reserves terabytes of shadow memory, which RLIMIT_DATA
+            // counts
+            limits.memory_mb = 0;
         }
-        "c++" | "cpp" => {
-            ext = "cpp";
-            cmd = "g++"; // Requires separate compile/run steps
+        let supervision = Supervision {
+            timeout: match settings.execution.timeout_secs {
+                0 => None,
+                secs => Some(Duration::from_secs(secs)),
+            },
+            limits,
+            sandbox: match settings.execution.mode {
+                ExecutionMode::Trusted => None,
+                ExecutionMode::Sandbox => Some(Sandbox::new(cwd)?),
+            },
+        };
+
+        let run_id = NEXT_RUN_ID.fetch_add(1, Ordering::Relaxed);
+        let build_dir = app.state::<BuildWorkspaces>().create(run_id)?;
+        // The build directory is removed again if the rest can't be set up
+        let prepared = (|| -> Result<Self, String> {
+            // Profiles and coverage change the single-file compile command, so
+            // asking for them builds just the file
+            let project = if settings.execution.detect_projects
+                && profile == BuildProfile::Standard
+                && !config.coverage
+                && !config.single_file
+            {
+                projects::detect(cwd, toolchain, &build_dir)?
+            } else {
+                None
+            };
+
+            let mut coverage = None;
+            let (compile, argv, env, source) = if let Some(project) = &project {
+                // Projects are built as they are on disk, in place
+                let compile = (!project.compile.is_empty()).then(|| CompileStep {
+                    commands: project.compile.clone(),
+                    dir: cwd.to_path_buf(),
+                    diagnostics: project.diagnostics,
+                    cache: None,
+                    supervised: true,
+                });
+                let argv = project.run_argv(&config.args);
+                (compile, argv, project.env.clone(), None)
+            } else {
+                // Single files are written to the run's build directory. The
+                // compiler runs there; the program itself runs in the working dir.
+                let file_path = build_dir.join(format!("main.{}", toolchain.extension));
+
+                let mut file = std::fs::File::create(&file_path).map_err(|e| e.to_string())?;
+                file.write_all(code.as_bytes()).map_err(|e| e.to_string())?;
+
+                #[cfg(target_os = "windows")]
+                let exe_name = "main.exe";
+                #[cfg(not(target_os = "windows"))]
+                let exe_name = "main";
+
+                let exe_path = build_dir.join(exe_name);
+                let paths = BuildPaths {
+                    source: &file_path,
+                    binary: &exe_path,
+                    dir: &run_dir,
+                };
+                if config.coverage {
+                    coverage = Some(Coverage::new(
+                        &toolchain.name,
+                        &build_dir,
+                        &file_path,
+                        &exe_path,
+                    )?);
+                }
+                let coverage_flags = coverage.as_ref().map_or(&[][..], |c| c.compile_flags());
+                let compile = toolchain.compile_argv(&paths)?.map(|mut argv| {
+                    argv.extend(extra_flags.iter().cloned());
+                    argv.extend(coverage_flags.iter().map(|flag| flag.to_string()));
+                    CompileStep {
+                        commands: vec![argv],
+                        dir: build_dir.clone(),
+                        diagnostics: toolchain.diagnostics,
+                        // A cached binary would come without its coverage notes,
+                        // and its debug info would name an earlier run's source
+                        cache: if settings.execution.compile_cache
+                            && coverage.is_none()
+                            && profile != BuildProfile::Debug
+                        {
+                            CacheSlot::new(toolchain, extra_flags, code, &exe_path)
+                        } else {
+                            None
+                        },
+                        supervised: false,
+                    }
+                });
+                let mut argv = toolchain.run_argv(&paths)?;
+                argv.extend(config.args.iter().cloned());
+                if let Some(coverage) = &mut coverage {
+                    argv = coverage.instrument(argv)?;
+                }
+                let env = coverage.as_ref().map(Coverage::env).unwrap_or_default();
+                (compile, argv, env, Some(file_path))
+            };
+
+            Ok(Self {
+                run_id,
+                compile,
+                run: RunStep {
+                    argv,
+                    env: env.into_iter().chain(config.env).collect(),
+                    dir: run_dir,
+                    stdin,
+                },
+                supervision,
+                build_dir: build_dir.clone(),
+                retain_builds: settings.execution.retain_builds,
+                project: project.map(|project| project.kind),
+                source,
+                coverage,
+            })
+        })();
+        if prepared.is_err() {
+            let _ = std::fs::remove_dir_all(&build_dir);
         }
-        "c" => {
-            ext = "c";
-            cmd = "gcc";
+        prepared
+    }
+
+    pub fn initial_status(&self) -> RunStatus {
+        if self.compile.is_some() {
+            RunStatus::Compiling
+        } else {
+            RunStatus::Running
         }
-        "rust" => {
-            ext = "rs";
-            cmd = "rustc";
+    }
+
+    /// Runs the compile step, if any, or restores its output from the
+    /// compile cache. Returns the compile phase's result when the build
+    /// failed or was killed, in which case nothing should run.
+    pub async fn build(&self, app: &AppHandle) -> Result<Option<ExecutionResult>, ExecutionResult> {
+        let Some(step) = &self.compile else {
+            return Ok(None);
+        };
+
+        let cache = app.state::<CompileCache>();
+        let cached = match &step.cache {
+            Some(slot) => cache.key(slot).await.map(|key| (slot, key)),
+            None => None,
+        };
+        if let Some((slot, key)) = &cached {
+            if let Some(diagnostics) = cache.restore(key, &slot.binary) {
+                let mut result = ExecutionResult::new(self.run_id, Phase::Compile);
+                result.cached_build = true;
+                result.diagnostics = diagnostics;
+                result.reason = app.state::<RunRegistry>().stop_reason(self.run_id);
+                if result.reason.is_some() {
+                    return Err(result);
+                }
+                return Ok(Some(result));
+            }
         }
-        "go" | "golang" => {
-            ext = "go";
-            cmd = "go";
-            args.push("run");
+
+        let supervision = step.supervised.then_some(&self.supervision);
+        let (mut result, built) = compile_program(app, self.run_id, step, supervision).await;
+
+        // A kill during compilation also cancels the run
+        result.reason = app.state::<RunRegistry>().stop_reason(self.run_id);
+        if !built || result.reason.is_some() {
+            return Err(result);
         }
-        _ => return Err(format!("Unsupported language for execution: {}", language)),
+        if let Some((slot, key)) = &cached {
+            // A failure to cache shouldn't fail the run
+            let _ = cache.store(key, slot, &result.diagnostics);
+        }
+        Ok(Some(result))
     }
 
-    // 1. Write current code to a file in CWD
-    let file_name = format!("main.{}", ext);
-    let file_path = cwd.join(&file_name);
+    /// Runs the built program once more with `input` as its stdin, without
+    /// forwarding its output to the frontend.
+    pub async fn run_with_input(&self, app: &AppHandle, input: &[u8]) -> ExecutionResult {
+        let run = RunStep {
+            stdin: Some(input.to_vec()),
+            ..self.run.clone()
+        };
+        let mut result = run_program(app, self.run_id, &run, &self.supervision, Echo::NONE).await;
+        result.sanitizer_findings = sanitizers::parse(&result.stderr, self.source());
+        result
+    }
 
-    let mut file = std::fs::File::create(&file_path).map_err(|e| e.to_string())?;
-    file.write_all(code.as_bytes()).map_err(|e| e.to_string())?;
-
-    // 2. Execute
-    let output = if language.to_lowercase() == "c++"
-        || language.to_lowercase() == "cpp"
-        || language.to_lowercase() == "c"
-        || language.to_lowercase() == "rust"
-    {
-        // Compiled languages: Compile then Run
-        #[cfg(target_os = "windows")]
-        let exe_name = "main.exe";
-        #[cfg(not(target_os = "windows"))]
-        let exe_name = "main";
-
-        let exe_path = cwd.join(exe_name);
-
-        // Compile
-        let mut compile_cmd = Command::new(cmd);
-        compile_cmd
-            .arg(&file_path)
-            .arg("-o")
-            .arg(&exe_path)
-            .arg(&exe_path)
-            .current_dir(&cwd); // Run compiler in CWD
-
-        #[cfg(target_os = "windows")]
-        compile_cmd.creation_flags(0x08000000);
-
-        let compile_output = compile_cmd
-            .output()
-            .map_err(|e| format!("Failed to run compiler: {}", e))?;
-
-        if !compile_output.status.success() {
-            return Ok(String::from_utf8_lossy(&compile_output.stderr).to_string());
-        }
-
-        // Run
-        // Run
-        let mut run_cmd = Command::new(&exe_path);
-        run_cmd.current_dir(&cwd);
-
-        #[cfg(target_os = "windows")]
-        run_cmd.creation_flags(0x08000000);
-
-        run_cmd
-            .output()
-            .map_err(|e| format!("Failed to run executable: {}", e))?
-    } else {
-        // Interpreted languages (Python, Node, Go Run)
-        // For Go run, we simply pass the filename
-        // Python/Node also take the filename
-        args.push(file_name.as_str());
+    /// The program's command line, arguments included.
+    pub fn run_argv(&self) -> &[String] {
+        &self.run.argv
+    }
 
-        let mut run_cmd = Command::new(cmd);
-        run_cmd.args(&args).current_dir(&cwd);
+    /// The file the code was written to, unless a project was detected.
+    pub fn source(&self) -> Option<&Path> {
+        self.source.as_deref()
+    }
 
-        #[cfg(target_os = "windows")]
-        run_cmd.creation_flags(0x08000000);
+    /// What the runs so far covered, when coverage is on.
+    pub async fn coverage(&self) -> Option<CoverageReport> {
+        match &self.coverage {
+            Some(coverage) => Some(coverage.report().await),
+            None => None,
+        }
+    }
 
-        run_cmd
-            .output()
-            .map_err(|e| format!("Failed to execute command: {}", e))?
+    /// The directory the program runs in.
+    pub fn run_dir(&self) -> &Path {
+        &self.run.dir
+    }
+
+    /// Hands the build directory back, to be kept or deleted per settings.
+    pub fn release(self, app: &AppHandle) {
+        app.state::<BuildWorkspaces>()
+            .release(self.build_dir, self.retain_builds);
+    }
+}
+
+/// Compiles (when the toolchain has a compile step) and then runs the
+/// program, stopping early if the build fails or the run is killed.
+async fn run_phases(app: &AppHandle, prepared: &PreparedRun) -> ExecutionResult {
+    let run_id = prepared.run_id;
+
+    // Compiled languages: Compile then Run
+    let compiled = match prepared.build(app).await {
+        Ok(compiled) => compiled,
+        Err(failed) => return failed,
     };
 
-    // 3. Collect Output
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let stderr = String::from_utf8_lossy(&output.stderr);
+    // A kill between the phases cancels the run
+    let registry = app.state::<RunRegistry>();
+    if let Some(reason) = registry.stop_reason(run_id) {
+        let mut cancelled = compiled.unwrap_or_else(|| ExecutionResult::new(run_id, Phase::Run));
+        cancelled.reason = Some(reason);
+        return cancelled;
+    }
 
-    if !stderr.is_empty() {
-        Ok(format!("{}\n{}", stdout, stderr))
-    } else {
-        Ok(stdout.to_string())
+    registry.set_status(run_id, RunStatus::Running);
+    let mut result =
+        run_program(app, run_id, &prepared.run, &prepared.supervision, Echo::ALL).await;
+    result.sanitizer_findings = sanitizers::parse(&result.stderr, prepared.source());
+    result.coverage = prepared.coverage().await;
+    if let Some(compiled) = compiled {
+        result.compile_time_ms = compiled.compile_time_ms;
+        result.diagnostics = compiled.diagnostics;
+        result.cached_build = compiled.cached_build;
+    }
+    result
+}
+
+/// Runs the compile commands in order, stopping at the first one that fails
+/// or when the run is killed. Returns the compile phase's result and whether
+/// the build succeeded.
+async fn compile_program(
+    app: &AppHandle,
+    run_id: u64,
+    step: &CompileStep,
+    supervision: Option<&Supervision>,
+) -> (ExecutionResult, bool) {
+    let registry = app.state::<RunRegistry>();
+    let mut result = ExecutionResult::new(run_id, Phase::Compile);
+    // JSON diagnostics are not for humans; the rendered text is emitted once
+    // the compiler is done instead
+    let echo = Echo {
+        stdout: true,
+        stderr: step.diagnostics != Some(DiagnosticFormat::Rustc),
+    };
+
+    let mut combined: Option<PhaseOutcome> = None;
+    for argv in &step.commands {
+        let cmd = command_for(argv, &step.dir);
+        // Builds get no input; an empty one closes their stdin
+        let input = supervision.map(|_| &[][..]);
+        let outcome = match stream_process(app, run_id, cmd, supervision, input, echo).await {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                result.error = Some(format!("Failed to run {}: {}", argv[0], e));
+                return (result, false);
+            }
+        };
+
+        let failed = !outcome.exit.status.success();
+        combined = Some(match combined {
+            Some(mut all) => {
+                all.stdout.push_str(&outcome.stdout);
+                all.stderr.push_str(&outcome.stderr);
+                all.elapsed += outcome.elapsed;
+                all.exit = outcome.exit;
+                all
+            }
+            None => outcome,
+        });
+        if failed || registry.stop_reason(run_id).is_some() {
+            break;
+        }
+    }
+
+    let Some(mut outcome) = combined else {
+        return (result, true);
+    };
+    result.compile_time_ms = Some(outcome.elapsed.as_millis() as u64);
+    let built = outcome.exit.status.success();
+    if let Some(format) = step.diagnostics {
+        let output = if format.uses_stdout() {
+            &outcome.stdout
+        } else {
+            &outcome.stderr
+        };
+        let parsed = diagnostics::parse(format, output, &step.dir);
+        if !echo.stderr && !parsed.rendered.is_empty() {
+            let _ = app.emit(
+                "execution-stderr",
+                OutputChunk {
+                    run_id,
+                    data: parsed.rendered.clone(),
+                },
+            );
+            outcome.stderr = parsed.rendered;
+        }
+        result.diagnostics = parsed.diagnostics;
+    }
+    result.record(outcome);
+
+    (result, built)
+}
+
+async fn run_program(
+    app: &AppHandle,
+    run_id: u64,
+    run: &RunStep,
+    supervision: &Supervision,
+    echo: Echo,
+) -> ExecutionResult {
+    let mut result = ExecutionResult::new(run_id, Phase::Run);
+
+    let mut cmd = command_for(&run.argv, &run.dir);
+    cmd.envs(run.env.iter().cloned());
+    let input = run.stdin.as_deref();
+    match stream_process(app, run_id, cmd, Some(supervision), input, echo).await {
+        Ok(outcome) => {
+            result.run_time_ms = Some(outcome.elapsed.as_millis() as u64);
+            result.run_elapsed = Some(outcome.elapsed);
+            result.peak_memory_kb = outcome.exit.peak_memory_kb;
+            result.record(outcome);
+            result.reason = app.state::<RunRegistry>().stop_reason(run_id);
+            result.success = result.exit_code == Some(0) && result.reason.is_none();
+        }
+        Err(e) => {
+            result.error = Some(format!("Failed to execute {}: {}", run.argv[0], e));
+        }
+    }
+
+    result
+}
+
+/// Builds a command from an expanded toolchain template, run in `cwd`.
+fn command_for(argv: &[String], cwd: &Path) -> tokio::process::Command {
+    let mut cmd = tokio::process::Command::new(&argv[0]);
+    cmd.args(&argv[1..]).current_dir(cwd);
+
+    #[cfg(target_os = "windows")]
+    cmd.creation_flags(0x08000000);
+
+    cmd
+}
+
+/// Spawns `cmd` and forwards its stdout/stderr to the frontend as they
+/// arrive. Under `supervision` (runs and project builds), the child's stdin is
+/// registered so `write_stdin` can reach it, resource limits and the sandbox
+/// are applied, and the child is killed once the timeout elapses. `input`,
+/// if given, is written to stdin instead, which is then closed.
+async fn stream_process(
+    app: &AppHandle,
+    run_id: u64,
+    mut cmd: tokio::process::Command,
+    supervision: Option<&Supervision>,
+    input: Option<&[u8]>,
+    echo: Echo,
+) -> Result<PhaseOutcome, String> {
+    #[cfg(unix)]
+    if let Some(supervision) = supervision {
+        supervision.limits.apply(cmd.as_std_mut());
+    }
+
+    #[cfg(target_os = "linux")]
+    if let Some(sandbox) = supervision.and_then(|s| s.sandbox.as_ref()) {
+        sandbox.apply(cmd.as_std_mut());
+    }
+    let sandboxed = supervision.is_some_and(|s| s.sandbox.is_some());
+
+    let started = Instant::now();
+    let mut process = Process::spawn(cmd, supervision.is_some()).map_err(|e| {
+        if sandboxed {
+            format!(
+                "{} (sandbox setup failed; unprivileged user namespaces may be disabled)",
+                e
+            )
+        } else {
+            e.to_string()
+        }
+    })?;
+
+    let stdout = process.stdout.take().ok_or("Failed to capture stdout")?;
+    let stderr = process.stderr.take().ok_or("Failed to capture stderr")?;
+    let registry = app.state::<RunRegistry>();
+    let mut fixture = None;
+    if let Some(stdin) = process.stdin.take() {
+        match input {
+            Some(input) => fixture = Some((stdin, input)),
+            None => registry.attach_stdin(run_id, stdin),
+        }
+    }
+    registry.attach_pid(run_id, process.pid);
+    // A kill that came in while the process was starting found no pid
+    if registry.stop_reason(run_id).is_some() {
+        let _ = process::kill_tree(process.pid);
+    }
+
+    let feed_stdin = async move {
+        if let Some((mut stdin, input)) = fixture {
+            // The program may exit without reading all of it
+            let _ = stdin.write_all(input).await;
+        }
+    };
+
+    let (stdout, stderr, exit, ()) = tokio::join!(
+        forward_output(
+            app,
+            run_id,
+            echo.stdout.then_some("execution-stdout"),
+            stdout
+        ),
+        forward_output(
+            app,
+            run_id,
+            echo.stderr.then_some("execution-stderr"),
+            stderr
+        ),
+        process.wait(supervision.and_then(|s| s.timeout)),
+        feed_stdin
+    );
+    let elapsed = started.elapsed();
+
+    registry.detach_stdin(run_id);
+    registry.detach_pid(run_id);
+
+    let exit = exit.map_err(|e| e.to_string())?;
+    if exit.timed_out {
+        registry.record_stop_reason(run_id, StopReason::Timeout);
+    }
+    let exceeded = supervision.and_then(|s| s.limits.exceeded(&exit.status, &stderr, false));
+    if let Some(reason) = exceeded {
+        registry.record_stop_reason(run_id, reason);
     }
+
+    Ok(PhaseOutcome {
+        exit,
+        stdout,
+        stderr,
+        elapsed,
+    })
 }
 
-use std::path::PathBuf;
+/// Reads `reader` until EOF, emitting each chunk under `event` if given.
+/// Bytes of a UTF-8 sequence split across reads are held back until the next
+/// chunk. Returns the first `MAX_CAPTURE_BYTES` of output.
+async fn forward_output<R: AsyncRead + Unpin>(
+    app: &AppHandle,
+    run_id: u64,
+    event: Option<&str>,
+    mut reader: R,
+) -> String {
+    let mut buf = [0u8; 4096];
+    let mut pending: Vec<u8> = Vec::new();
+    let mut captured = String::new();
+
+    loop {
+        let n = match reader.read(&mut buf).await {
+            Ok(0) | Err(_) => break,
+            Ok(n) => n,
+        };
+        pending.extend_from_slice(&buf[..n]);
+
+        let valid = match std::str::from_utf8(&pending) {
+            Ok(_) => pending.len(),
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(_) => pending.len(),
+        };
+        if valid == 0 {
+            continue;
+        }
+
+        let data = String::from_utf8_lossy(&pending[..valid]).to_string();
+        pending.drain(..valid);
+        capture(&mut captured, &data);
+        if let Some(event) = event {
+            let _ = app.emit(event, OutputChunk { run_id, data });
+        }
+    }
+
+    if !pending.is_empty() {
+        let data = String::from_utf8_lossy(&pending).to_string();
+        capture(&mut captured, &data);
+        if let Some(event) = event {
+            let _ = app.emit(event, OutputChunk { run_id, data });
+        }
+    }
+
+    captured
+}
+
+fn capture(captured: &mut String, data: &str) {
+    let room = MAX_CAPTURE_BYTES.saturating_sub(captured.len());
+    let mut end = room.min(data.len());
+    while !data.is_char_boundary(end) {
+        end -= 1;
+    }
+    captured.push_str(&data[..end]);
+}
+
+use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 use tauri::State;
 
 pub struct AppState {
     pub cwd: Mutex<PathBuf>,
+    /// The folder opened as the project. `cwd` moves with the terminal's
+    /// `cd`; this only changes when another folder is opened.
+    pub project_root: Mutex<PathBuf>,
+}
+
+/// Commands started by `run_terminal_command`, by pid, and whether each
+/// has been interrupted.
+#[derive(Default)]
+pub struct TerminalCommands {
+    running: Mutex<HashMap<u32, bool>>,
+}
+
+impl TerminalCommands {
+    fn insert(&self, pid: u32) {
+        if let Ok(mut running) = self.running.lock() {
+            running.insert(pid, false);
+        }
+    }
+
+    /// Forgets a finished command. Returns whether it was interrupted.
+    fn remove(&self, pid: u32) -> bool {
+        self.running
+            .lock()
+            .ok()
+            .and_then(|mut running| running.remove(&pid))
+            .unwrap_or(false)
+    }
+
+    fn interrupt_all(&self) -> Result<bool, String> {
+        let mut running = self.running.lock().map_err(|e| e.to_string())?;
+        for (pid, interrupted) in running.iter_mut() {
+            process::interrupt_tree(*pid).map_err(|e| e.to_string())?;
+            *interrupted = true;
+        }
+        Ok(!running.is_empty())
+    }
+}
+
+/// Programs started by `execute_code`, keyed by run id. The last
+/// `MAX_FINISHED_RUNS` finished runs stay in the map so their final status
+/// can still be queried.
+#[derive(Default)]
+pub struct RunRegistry {
+    runs: Mutex<HashMap<u64, RunEntry>>,
+    /// Finished runs still in `runs`, oldest first.
+    finished: Mutex<VecDeque<u64>>,
+}
+
+struct RunEntry {
+    status: RunStatus,
+    stdin: Option<Arc<tokio::sync::Mutex<ChildStdin>>>,
+    pid: Option<u32>,
+    stop_reason: Option<StopReason>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum RunStatus {
+    Compiling,
+    Running,
+    Finished(Box<ExecutionResult>),
+}
+
+impl RunRegistry {
+    pub(crate) fn insert(&self, run_id: u64, status: RunStatus) {
+        if let Ok(mut runs) = self.runs.lock() {
+            runs.insert(
+                run_id,
+                RunEntry {
+                    status,
+                    stdin: None,
+                    pid: None,
+                    stop_reason: None,
+                },
+            );
+        }
+    }
+
+    pub(crate) fn set_status(&self, run_id: u64, status: RunStatus) {
+        let Ok(mut runs) = self.runs.lock() else {
+            return;
+        };
+        let finished = matches!(status, RunStatus::Finished(_));
+        if let Some(entry) = runs.get_mut(&run_id) {
+            entry.status = status;
+        }
+
+        if finished {
+            if let Ok(mut order) = self.finished.lock() {
+                order.push_back(run_id);
+                while order.len() > MAX_FINISHED_RUNS {
+                    if let Some(oldest) = order.pop_front() {
+                        runs.remove(&oldest);
+                    }
+                }
+            }
+        }
+    }
+
+    fn attach_stdin(&self, run_id: u64, stdin: ChildStdin) {
+        if let Ok(mut runs) = self.runs.lock() {
+            if let Some(entry) = runs.get_mut(&run_id) {
+                entry.stdin = Some(Arc::new(tokio::sync::Mutex::new(stdin)));
+            }
+        }
+    }
+
+    /// Drops the registry's handle to the child's stdin, which closes the
+    /// pipe once no write is in flight.
+    fn detach_stdin(&self, run_id: u64) -> bool {
+        match self.runs.lock() {
+            Ok(mut runs) => runs
+                .get_mut(&run_id)
+                .and_then(|entry| entry.stdin.take())
+                .is_some(),
+            Err(_) => false,
+        }
+    }
+
+    fn attach_pid(&self, run_id: u64, pid: u32) {
+        if let Ok(mut runs) = self.runs.lock() {
+            if let Some(entry) = runs.get_mut(&run_id) {
+                entry.pid = Some(pid);
+            }
+        }
+    }
+
+    fn detach_pid(&self, run_id: u64) {
+        if let Ok(mut runs) = self.runs.lock() {
+            if let Some(entry) = runs.get_mut(&run_id) {
+                entry.pid = None;
+            }
+        }
+    }
+
+    fn record_stop_reason(&self, run_id: u64, reason: StopReason) {
+        if let Ok(mut runs) = self.runs.lock() {
+            if let Some(entry) = runs.get_mut(&run_id) {
+                entry.stop_reason.get_or_insert(reason);
+            }
+        }
+    }
+
+    /// Forgets a recorded stop reason, so a run that goes on to start
+    /// another process isn't reported as stopped again.
+    pub(crate) fn clear_stop_reason(&self, run_id: u64) {
+        if let Ok(mut runs) = self.runs.lock() {
+            if let Some(entry) = runs.get_mut(&run_id) {
+                entry.stop_reason = None;
+            }
+        }
+    }
+
+    pub(crate) fn remove(&self, run_id: u64) {
+        if let Ok(mut runs) = self.runs.lock() {
+            runs.remove(&run_id);
+        }
+    }
+
+    pub(crate) fn stop_reason(&self, run_id: u64) -> Option<StopReason> {
+        let runs = self.runs.lock().ok()?;
+        runs.get(&run_id).and_then(|entry| entry.stop_reason)
+    }
+
+    /// Records why the run is being stopped and kills its current process.
+    /// Killing a run between its compile and run phases just cancels it.
+    fn kill(&self, run_id: u64, reason: StopReason) -> std::io::Result<()> {
+        let mut runs = self
+            .runs
+            .lock()
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+        let entry = runs.get_mut(&run_id).ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("Unknown run: {}", run_id),
+            )
+        })?;
+
+        entry.stop_reason.get_or_insert(reason);
+        match entry.pid {
+            Some(pid) => process::kill_tree(pid),
+            None => Ok(()),
+        }
+    }
+
+    /// Kills every run that still has a live process. Called on app exit.
+    pub fn kill_all(&self) {
+        if let Ok(mut runs) = self.runs.lock() {
+            for entry in runs.values_mut() {
+                if let Some(pid) = entry.pid.take() {
+                    entry.stop_reason.get_or_insert(StopReason::Killed);
+                    let _ = process::kill_tree(pid);
+                }
+            }
+        }
+    }
+
+    fn stdin(&self, run_id: u64) -> Result<Arc<tokio::sync::Mutex<ChildStdin>>, String> {
+        let runs = self.runs.lock().map_err(|e| e.to_string())?;
+        let entry = runs
+            .get(&run_id)
+            .ok_or_else(|| format!("Unknown run: {}", run_id))?;
+        entry
+            .stdin
+            .clone()
+            .ok_or_else(|| format!("Run {} is not accepting input", run_id))
+    }
+}
+
+#[tauri::command]
+pub async fn write_stdin(
+    run_id: u64,
+    line: String,
+    registry: State<'_, RunRegistry>,
+) -> Result<(), String> {
+    let stdin = registry.stdin(run_id)?;
+    let mut stdin = stdin.lock().await;
+
+    let mut data = line.into_bytes();
+    if data.last() != Some(&b'\n') {
+        data.push(b'\n');
+    }
+
+    stdin
+        .write_all(&data)
+        .await
+        .map_err(|e| format!("Failed to write to stdin: {}", e))?;
+    stdin.flush().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn kill_execution(run_id: u64, registry: State<'_, RunRegistry>) -> Result<(), String> {
+    registry
+        .kill(run_id, StopReason::Killed)
+        .map_err(|e| format!("Failed to kill run {}: {}", run_id, e))
 }
 
+#[tauri::command]
+pub fn close_stdin(run_id: u64, registry: State<'_, RunRegistry>) -> Result<(), String> {
+    if registry.detach_stdin(run_id) {
+        Ok(())
+    } else {
+        Err(format!("Run {} is not accepting input", run_id))
+    }
+}
+
+#[tauri::command]
+pub fn execution_status(
+    run_id: u64,
+    registry: State<'_, RunRegistry>,
+) -> Result<RunStatus, String> {
+    let runs = registry.runs.lock().map_err(|e| e.to_string())?;
+    runs.get(&run_id)
+        .map(|entry| entry.status.clone())
+        .ok_or_else(|| format!("Unknown run: {}", run_id))
+}
+
+/// Runs one line typed into the terminal panel and returns its combined
+/// output. The line is split at `&&`, `||` and `;`; builtins (`cd`,
+/// `pushd`, `popd`, `dirs`, `export`, `unset`) run here against the shell
+/// session named `session`, and everything else in a fresh shell with the
+/// session's variables. The cwd lock is never held while a command runs;
+/// `interrupt_terminal_command` stops it, and the rest of the line.
 #[tauri::command]
 pub async fn run_terminal_command(
     command: String,
+    session: Option<String>,
     state: State<'_, AppState>,
+    commands: State<'_, TerminalCommands>,
+    shells: State<'_, ShellSessions>,
 ) -> Result<String, String> {
-    let mut cwd = state.cwd.lock().map_err(|e| e.to_string())?;
+    let session = session.unwrap_or_else(|| shell::DEFAULT_SESSION.to_string());
+    let mut outputs: Vec<String> = Vec::new();
+    let mut succeeded = true;
 
-    // Handle "cd" command manually
-    let trimmed = command.trim();
-    if trimmed.starts_with("cd ") || trimmed == "cd" {
-        let path_str = if trimmed == "cd" {
-            // Default to Home if just "cd" (or could be nothing, but let's say home)
-            // On Windows, USERPROFILE. On Unix, HOME.
-            dirs::home_dir()
-                .unwrap_or(PathBuf::from("."))
-                .to_string_lossy()
-                .to_string()
-        } else {
-            trimmed[3..].trim().to_string()
-        };
+    for (connector, command) in shell::split_commands(&command) {
+        if !connector.should_run(succeeded) {
+            continue;
+        }
 
-        let new_path = if path_str == "~" {
-            dirs::home_dir().unwrap_or(PathBuf::from("."))
-        } else {
-            cwd.join(&path_str)
-        };
+        if let Some(outcome) = shells.run_builtin(&session, &command, &state.cwd)? {
+            outputs.push(outcome.output);
+            succeeded = outcome.success;
+            continue;
+        }
 
-        // Canonicalize to resolve .. and symlinks
-        match std::fs::canonicalize(&new_path) {
-            Ok(p) => {
-                *cwd = p;
-                return Ok(String::new()); // No output for successful cd
-            }
-            Err(e) => {
-                return Ok(format!("cd: {}: {}", path_str, e));
-            }
+        let cwd = state.cwd.lock().map_err(|e| e.to_string())?.clone();
+        let outcome = run_in_shell(&command, &cwd, shells.env(&session), &commands).await?;
+        outputs.push(outcome.output);
+        succeeded = outcome.success;
+        if outcome.interrupted {
+            break;
         }
     }
 
+    Ok(outputs
+        .iter()
+        .map(|output| output.trim_end_matches('\n'))
+        .filter(|output| !output.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+struct ShellOutcome {
+    output: String,
+    success: bool,
+    interrupted: bool,
+}
+
+async fn run_in_shell(
+    command: &str,
+    cwd: &Path,
+    env: Vec<(String, Option<String>)>,
+    commands: &TerminalCommands,
+) -> Result<ShellOutcome, String> {
     // Execute command in the current CWD
     #[cfg(target_os = "windows")]
     let (shell, arg) = ("powershell", "-NoProfile");
@@ -181,30 +1136,76 @@ pub async fn run_terminal_command(
     // Windows PowerShell: powershell -NoProfile -Command "Get-Location"
     // Unix: sh -c "pwd"
 
-    let mut cmd = Command::new(shell);
+    let mut cmd = tokio::process::Command::new(shell);
 
     #[cfg(target_os = "windows")]
-    cmd.args(&[arg, "-Command", &command]);
+    cmd.args([arg, "-Command", command]);
 
     #[cfg(not(target_os = "windows"))]
-    cmd.args(&[arg, &command]);
+    cmd.args([arg, command]);
 
     #[cfg(target_os = "windows")]
     cmd.creation_flags(0x08000000);
 
-    let output = cmd
-        .current_dir(&*cwd)
-        .output()
-        .map_err(|e| format!("Failed to execute command: {}", e))?;
+    for (name, value) in env {
+        match value {
+            Some(value) => cmd.env(name, value),
+            None => cmd.env_remove(name),
+        };
+    }
+
+    let limits = settings::load(cwd)?.limits;
+    #[cfg(unix)]
+    limits.apply(cmd.as_std_mut());
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let stderr = String::from_utf8_lossy(&output.stderr);
+    cmd.current_dir(cwd);
+    let mut process =
+        Process::spawn(cmd, false).map_err(|e| format!("Failed to execute command: {}", e))?;
+    let pid = process.pid;
+    commands.insert(pid);
 
-    if !stderr.is_empty() {
-        Ok(format!("{}\n{}", stdout, stderr))
+    let (stdout, stderr) = tokio::join!(
+        read_to_string(process.stdout.take()),
+        read_to_string(process.stderr.take())
+    );
+    let exit = process.wait(None).await;
+    let interrupted = commands.remove(pid);
+    let exit = exit.map_err(|e| format!("Failed to execute command: {}", e))?;
+
+    let mut output = if !stderr.is_empty() {
+        format!("{}\n{}", stdout, stderr)
     } else {
-        Ok(stdout.to_string())
+        stdout
+    };
+
+    if interrupted {
+        output.push_str("\n^C");
+    } else if let Some(reason) = limits.exceeded(&exit.status, &stderr, true) {
+        output.push_str(&format!("\n[{}]", reason.describe()));
+    }
+
+    Ok(ShellOutcome {
+        output,
+        success: exit.status.success(),
+        interrupted,
+    })
+}
+
+async fn read_to_string<R: AsyncRead + Unpin>(reader: Option<R>) -> String {
+    let mut bytes = Vec::new();
+    if let Some(mut reader) = reader {
+        let _ = reader.read_to_end(&mut bytes).await;
     }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// Sends Ctrl+C (SIGINT to the process group) to every command started by
+/// `run_terminal_command` that is still running. On Windows, where there is
+/// no console to deliver it through, they are killed instead. Returns
+/// whether anything was running.
+#[tauri::command]
+pub fn interrupt_terminal_command(commands: State<'_, TerminalCommands>) -> Result<bool, String> {
+    commands.interrupt_all()
 }
 
 #[tauri::command]
@@ -224,6 +1225,7 @@ pub fn change_working_directory(
         return Err(format!("Path is not a directory: {}", path));
     }
 
+    *state.project_root.lock().map_err(|e| e.to_string())? = new_path.clone();
     *cwd = new_path;
     Ok("CWD Updated".to_string())
 }
@@ -250,6 +1252,7 @@ pub fn ensure_testing_grounds(state: State<'_, AppState>) -> Result<String, Stri
     // Set CWD to this valid path
     let mut cwd = state.cwd.lock().map_err(|e| e.to_string())?;
     *cwd = tg_path.clone();
+    *state.project_root.lock().map_err(|e| e.to_string())? = tg_path.clone();
 
     Ok(tg_path.to_string_lossy().to_string())
 }
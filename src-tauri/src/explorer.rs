@@ -0,0 +1,676 @@
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+
+use crate::diagnostics::{self, Diagnostic};
+use crate::execution::AppState;
+use crate::process;
+use crate::settings;
+use crate::toolchains::{self, BuildPaths, BuildProfile, EmitKind};
+use crate::workspace::BuildWorkspaces;
+
+/// The compiler is killed past this.
+const EXPLORE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Assembler directives that hold data the code refers to, like string
+/// literals and jump tables. Every other directive is dropped.
+const DATA_DIRECTIVES: [&str; 13] = [
+    "string", "ascii", "asciz", "byte", "short", "value", "word", "long", "int", "quad", "zero",
+    "float", "double",
+];
+
+#[derive(Serialize)]
+pub struct Exploration {
+    pub kind: EmitKind,
+    pub profile: BuildProfile,
+    pub success: bool,
+    /// The compiler's messages, as the terminal shows them.
+    pub compiler_output: String,
+    pub diagnostics: Vec<Diagnostic>,
+    /// The output without directives and debug info, one entry per line.
+    pub lines: Vec<OutputLine>,
+    /// Runs of consecutive lines that came from the same source line, for
+    /// highlighting. `end` is exclusive.
+    pub ranges: Vec<LineRange>,
+    /// Whether symbols were demangled, which needs `c++filt`.
+    pub demangled: bool,
+}
+
+#[derive(Serialize)]
+pub struct OutputLine {
+    pub text: String,
+    pub source_line: Option<u32>,
+}
+
+#[derive(Serialize)]
+pub struct LineRange {
+    pub source_line: u32,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Compiles the code to assembly or LLVM IR with the given build profile
+/// (the project's default one without it) and maps the result back to the
+/// source lines it came from.
+#[tauri::command]
+pub async fn explore_code(
+    app: AppHandle,
+    language: String,
+    code: String,
+    kind: EmitKind,
+    profile: Option<BuildProfile>,
+    state: State<'_, AppState>,
+) -> Result<Exploration, String> {
+    let cwd = state.cwd.lock().map_err(|e| e.to_string())?.clone();
+    let settings = settings::load(&cwd)?;
+    let toolchains = toolchains::load(&app, &cwd)?;
+    let toolchain = toolchains
+        .find(&language)
+        .ok_or_else(|| format!("Unsupported language for exploration: {}", language))?;
+    let emit_flags = toolchain.emit_flags(kind).ok_or_else(|| {
+        format!(
+            "{} output is not available for {}",
+            kind.name(),
+            toolchain.name
+        )
+    })?;
+    let (profile, profile_flags) =
+        toolchain.select_profile(profile, settings.execution.build_profile)?;
+
+    let scratch = app.state::<BuildWorkspaces>().create_scratch("explore")?;
+    let source = scratch.join(format!("main.{}", toolchain.extension));
+    let output = scratch.join(match kind {
+        EmitKind::Asm => "main.s",
+        EmitKind::LlvmIr => "main.ll",
+    });
+    let paths = BuildPaths {
+        source: &source,
+        binary: &output,
+        dir: &scratch,
+    };
+
+    let result = async {
+        std::fs::write(&source, &code).map_err(|e| e.to_string())?;
+        let mut argv = toolchain
+            .compile_argv(&paths)?
+            .ok_or_else(|| format!("{} has no compile step", toolchain.name))?;
+        argv.extend(profile_flags.iter().cloned());
+        argv.extend(emit_flags.iter().cloned());
+
+        let mut cmd = tokio::process::Command::new(&argv[0]);
+        cmd.args(&argv[1..]).current_dir(&scratch);
+        let compiled = process::output(cmd, None, EXPLORE_TIMEOUT).await?;
+
+        let (compiler_output, diagnostics) = match toolchain.diagnostics {
+            Some(format) => {
+                let text = if format.uses_stdout() {
+                    &compiled.stdout
+                } else {
+                    &compiled.stderr
+                };
+                let parsed = diagnostics::parse(format, &String::from_utf8_lossy(text), &scratch);
+                (parsed.rendered, parsed.diagnostics)
+            }
+            None => (
+                String::from_utf8_lossy(&compiled.stderr).into_owned(),
+                Vec::new(),
+            ),
+        };
+
+        let mut exploration = Exploration {
+            kind,
+            profile,
+            success: false,
+            compiler_output,
+            diagnostics,
+            lines: Vec::new(),
+            ranges: Vec::new(),
+            demangled: false,
+        };
+        let Ok(text) = std::fs::read_to_string(&output) else {
+            return Ok(exploration);
+        };
+        if !compiled.status.success() {
+            return Ok(exploration);
+        }
+
+        let source_name = format!("main.{}", toolchain.extension);
+        let mut lines = match kind {
+            EmitKind::Asm => filter_asm(&text, &source_name),
+            EmitKind::LlvmIr => filter_ir(&text, &source_name),
+        };
+        exploration.demangled = demangle(&mut lines).await;
+        exploration.ranges = line_ranges(&lines);
+        exploration.lines = lines;
+        exploration.success = true;
+        Ok(exploration)
+    }
+    .await;
+
+    let _ = std::fs::remove_dir_all(&scratch);
+    result
+}
+
+/// Keeps the instructions, the labels that are functions or are referred
+/// to, and data; drops the other directives, comments and the debug
+/// sections. `.loc` directives give each instruction its source line.
+fn filter_asm(asm: &str, source_name: &str) -> Vec<OutputLine> {
+    enum Entry<'a> {
+        Label(&'a str),
+        Line(String, Option<u32>),
+    }
+
+    let mut entries = Vec::new();
+    let mut used: HashSet<&str> = HashSet::new();
+    // `.file` numbers, and whether each is the source file
+    let mut files: HashMap<&str, bool> = HashMap::new();
+    let mut current = None;
+    let mut in_debug = false;
+
+    for line in asm.lines() {
+        let line = strip_asm_comment(line).trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with("//") {
+            continue;
+        }
+
+        if let Some(label) = line
+            .strip_suffix(':')
+            .filter(|l| !l.contains(char::is_whitespace))
+        {
+            if !in_debug {
+                entries.push(Entry::Label(label));
+            }
+            continue;
+        }
+
+        if let Some(directive) = line.strip_prefix('.') {
+            let (name, args) = directive
+                .split_once(char::is_whitespace)
+                .unwrap_or((directive, ""));
+            let args = args.trim();
+            match name {
+                "file" => {
+                    if let Some((number, names)) = args.split_once(char::is_whitespace) {
+                        // `.file N "dir" "name"` or `.file N "path"`
+                        let file = names.split('"').skip(1).step_by(2).last();
+                        let is_source = file
+                            .and_then(|f| Path::new(f).file_name())
+                            .is_some_and(|n| n.to_string_lossy() == source_name);
+                        files.insert(number, is_source);
+                    }
+                }
+                "loc" => {
+                    let mut parts = args.split_whitespace();
+                    let in_source = parts.next().and_then(|f| files.get(f)) == Some(&true);
+                    let line: Option<u32> = parts.next().and_then(|l| l.parse().ok());
+                    // Line 0 is code the compiler made up
+                    current = line.filter(|&l| in_source && l > 0);
+                }
+                "section" => {
+                    let section = args.split(',').next().unwrap_or("").trim_matches('"');
+                    in_debug = section.starts_with(".debug")
+                        || section.starts_with(".note")
+                        || section.starts_with(".comment");
+                }
+                "text" | "data" | "bss" => in_debug = false,
+                _ if !in_debug && DATA_DIRECTIVES.contains(&name) => {
+                    entries.push(Entry::Line(format!("  .{} {}", name, args), None));
+                    used.extend(local_labels(args));
+                }
+                _ => {}
+            }
+            continue;
+        }
+
+        if !in_debug {
+            let instruction = line.split_whitespace().collect::<Vec<_>>().join(" ");
+            used.extend(local_labels(line));
+            entries.push(Entry::Line(format!("  {}", instruction), current));
+        }
+    }
+
+    entries
+        .into_iter()
+        .filter_map(|entry| match entry {
+            Entry::Label(label) if label.starts_with(".L") && !used.contains(label) => None,
+            Entry::Label(label) => Some(OutputLine {
+                text: format!("{}:", label),
+                source_line: None,
+            }),
+            Entry::Line(text, source_line) => Some(OutputLine { text, source_line }),
+        })
+        .collect()
+}
+
+/// Drops a trailing comment the compiler aligned after an instruction or
+/// label. A `#` right after an operand separator is an ARM immediate.
+fn strip_asm_comment(line: &str) -> &str {
+    ["\t#", "  #", "\t//", "  //"]
+        .iter()
+        .filter_map(|marker| line.find(marker))
+        .min()
+        .map_or(line, |at| &line[..at])
+}
+
+/// Local labels (`.L3`, `.LC0`, `.LBB0_2`) an instruction or datum names.
+fn local_labels(text: &str) -> impl Iterator<Item = &str> {
+    text.match_indices(".L").map(move |(at, _)| {
+        let end = text[at + 1..]
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '$' || c == '.'))
+            .map_or(text.len(), |end| at + 1 + end);
+        &text[at..end]
+    })
+}
+
+/// Keeps the module's code and declarations, drops the debug metadata,
+/// attributes and debug intrinsics. Each instruction's `!dbg` location
+/// gives its source line; code inlined from elsewhere is attributed to the
+/// line it was inlined at.
+fn filter_ir(ir: &str, source_name: &str) -> Vec<OutputLine> {
+    let metadata: HashMap<&str, &str> = ir
+        .lines()
+        .filter_map(|line| {
+            let (id, node) = line.strip_prefix('!')?.split_once(" = ")?;
+            id.bytes().all(|b| b.is_ascii_digit()).then_some((id, node))
+        })
+        .collect();
+
+    let mut lines: Vec<OutputLine> = Vec::new();
+    for line in ir.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('!')
+            || trimmed.starts_with("attributes #")
+            || trimmed.starts_with("#dbg_")
+            || trimmed.contains("@llvm.dbg.")
+        {
+            continue;
+        }
+        // One blank line between definitions is enough
+        if trimmed.is_empty() && lines.last().is_none_or(|l| l.text.is_empty()) {
+            continue;
+        }
+
+        let (text, source_line) = match line.split_once(" !dbg !") {
+            Some((code, rest)) => {
+                let id_len = rest.bytes().take_while(u8::is_ascii_digit).count();
+                let source_line = ir_line(&metadata, &rest[..id_len], source_name);
+                let code = code.strip_suffix(',').unwrap_or(code);
+                (format!("{}{}", code, &rest[id_len..]), source_line)
+            }
+            None => (line.to_string(), None),
+        };
+        // A definition's own `!dbg` is its subprogram, not a location
+        let source_line = source_line.filter(|_| !trimmed.starts_with("define "));
+        lines.push(OutputLine {
+            text: text.trim_end().to_string(),
+            source_line,
+        });
+    }
+    while lines.last().is_some_and(|l| l.text.is_empty()) {
+        lines.pop();
+    }
+    lines
+}
+
+/// The source line of a `DILocation`, following `inlinedAt` out to the code
+/// it was inlined into. `None` when that isn't the source file.
+fn ir_line(metadata: &HashMap<&str, &str>, id: &str, source_name: &str) -> Option<u32> {
+    let mut node = *metadata.get(id)?;
+    // Bounded, in case the metadata loops
+    for _ in 0..64 {
+        if !node.contains("DILocation(") {
+            return None;
+        }
+        if let Some(inlined_at) = ir_field(node, "inlinedAt: !") {
+            node = metadata.get(inlined_at)?;
+            continue;
+        }
+
+        let line: u32 = ir_field(node, "line: ")?.parse().ok()?;
+        let scope = metadata.get(ir_field(node, "scope: !")?)?;
+        let file = metadata.get(ir_field(scope, "file: !")?)?;
+        let filename = file.split_once("filename: \"")?.1.split('"').next()?;
+        let in_source = Path::new(filename)
+            .file_name()
+            .is_some_and(|n| n.to_string_lossy() == source_name);
+        return (in_source && line > 0).then_some(line);
+    }
+    None
+}
+
+/// The value of `field` in a metadata node, up to the next `,` or `)`.
+fn ir_field<'a>(node: &'a str, field: &str) -> Option<&'a str> {
+    let rest = node.split_once(field)?.1;
+    let end = rest.find([',', ')']).unwrap_or(rest.len());
+    Some(&rest[..end])
+}
+
+/// Demangles C++ and Rust symbols in place with `c++filt`, which knows
+/// both. Returns false when it isn't installed or fails, leaving the
+/// symbols as they were.
+async fn demangle(lines: &mut [OutputLine]) -> bool {
+    let mut symbols: Vec<&str> = Vec::new();
+    let mut seen = HashSet::new();
+    for line in lines.iter() {
+        for symbol in mangled_symbols(&line.text) {
+            if seen.insert(symbol) {
+                symbols.push(symbol);
+            }
+        }
+    }
+    if symbols.is_empty() {
+        return true;
+    }
+    let Some(program) = toolchains::find_program("c++filt") else {
+        return false;
+    };
+
+    let input = symbols.join("\n") + "\n";
+    let cmd = tokio::process::Command::new(program);
+    let Ok(output) = process::output(cmd, Some(input.as_bytes()), EXPLORE_TIMEOUT).await else {
+        return false;
+    };
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let demangled: Vec<&str> = stdout.lines().collect();
+    if !output.status.success() || demangled.len() != symbols.len() {
+        return false;
+    }
+
+    let names: HashMap<String, String> = symbols
+        .iter()
+        .zip(demangled)
+        .map(|(symbol, name)| (symbol.to_string(), strip_rust_hashes(name)))
+        .collect();
+    for line in lines.iter_mut() {
+        let mut text = String::with_capacity(line.text.len());
+        let mut rest = line.text.as_str();
+        while let Some(symbol) = mangled_symbols(rest).next() {
+            let at = symbol.as_ptr() as usize - rest.as_ptr() as usize;
+            text.push_str(&rest[..at]);
+            text.push_str(names.get(symbol).map_or(symbol, String::as_str));
+            rest = &rest[at + symbol.len()..];
+        }
+        text.push_str(rest);
+        line.text = text;
+    }
+    true
+}
+
+/// Itanium (`_Z`, `__Z` on macOS) and Rust v0 (`_R`) mangled names. Rust's
+/// legacy scheme is Itanium's.
+fn mangled_symbols(text: &str) -> impl Iterator<Item = &str> {
+    let is_symbol_char = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '$' || c == '.';
+    text.match_indices('_').filter_map(move |(at, _)| {
+        let starts_word = text[..at]
+            .chars()
+            .next_back()
+            .is_none_or(|c| !is_symbol_char(c));
+        let rest = &text[at..];
+        let prefixed = ["_Z", "__Z", "_R"].iter().any(|p| {
+            rest.starts_with(p)
+                && rest[p.len()..]
+                    .starts_with(|c: char| c.is_ascii_uppercase() || c.is_ascii_digit())
+        });
+        if !starts_word || !prefixed {
+            return None;
+        }
+        let end = rest
+            .find(|c: char| !is_symbol_char(c))
+            .unwrap_or(rest.len());
+        Some(&rest[..end])
+    })
+}
+
+/// Drops the hashes that only tell instances of Rust items apart: the
+/// `::h0123456789abcdef` legacy symbols end in and the `[0123456789abcdef]`
+/// v0 symbols put after crate names.
+fn strip_rust_hashes(name: &str) -> String {
+    let is_hash = |hash: &str| hash.len() == 16 && hash.bytes().all(|b| b.is_ascii_hexdigit());
+    let name = match name.rsplit_once("::h") {
+        Some((path, hash)) if is_hash(hash) => path,
+        _ => name,
+    };
+
+    let mut stripped = String::with_capacity(name.len());
+    let mut rest = name;
+    while let Some(at) = rest.find('[') {
+        let hash = rest[at + 1..].split(']').next().unwrap_or("");
+        stripped.push_str(&rest[..at]);
+        if is_hash(hash) && rest[at + 1 + hash.len()..].starts_with(']') {
+            rest = &rest[at + hash.len() + 2..];
+        } else {
+            stripped.push('[');
+            rest = &rest[at + 1..];
+        }
+    }
+    stripped.push_str(rest);
+    stripped
+}
+
+fn line_ranges(lines: &[OutputLine]) -> Vec<LineRange> {
+    let mut ranges: Vec<LineRange> = Vec::new();
+    for (index, line) in lines.iter().enumerate() {
+        let Some(source_line) = line.source_line else {
+            continue;
+        };
+        match ranges.last_mut() {
+            Some(range) if range.source_line == source_line && range.end == index => range.end += 1,
+            _ => ranges.push(LineRange {
+                source_line,
+                start: index,
+                end: index + 1,
+            }),
+        }
+    }
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// gcc's output for a `square` function and a `main` that calls it,
+    /// cut down.
+    const GCC_ASM: &str = r#"
+	.file	"sq.c"
+	.text
+.Ltext0:
+	.file 0 "/tmp/build" "sq.c"
+	.globl	square
+	.type	square, @function
+square:
+.LVL0:
+	.file 1 "sq.c"
+	.loc 1 2 19 view -0
+	.cfi_startproc
+	.loc 1 3 14 is_stmt 0 view .LVU2
+	imull	%edi, %edi
+	.loc 1 4 1 view .LVU3
+	movl	%edi, %eax
+	ret
+	.cfi_endproc
+	.section	.rodata.str1.1,"aMS",@progbits,1
+.LC0:
+	.string	"%d\n"
+	.text
+main:
+	.loc 1 6 5 view .LVU5
+	leaq	.LC0(%rip), %rdi
+	call	printf@PLT
+	.loc 1 0 0
+	movl	$0, %eax
+	.file 2 "/usr/include/stdio.h"
+	.loc 2 12 1
+	ret
+	.section	.debug_info,"",@progbits
+.Ldebug_info0:
+	.long	0x8e
+	.string	"GNU C17"
+"#;
+
+    const CLANG_IR: &str = r#"; ModuleID = 'sq.c'
+source_filename = "sq.c"
+
+define dso_local i32 @square(i32 noundef %0) #0 !dbg !10 {
+  %2 = mul nsw i32 %0, %0, !dbg !15
+  ret i32 %2, !dbg !16
+}
+
+define dso_local i32 @main() #0 !dbg !17 {
+    #dbg_value(i32 3, !12, !DIExpression(), !20)
+  call void @llvm.dbg.value(metadata i32 3, metadata !12, metadata !DIExpression()), !dbg !20
+  %1 = mul nsw i32 3, 3, !dbg !21
+  ret i32 0, !dbg !22
+}
+
+declare void @llvm.dbg.value(metadata, metadata, metadata) #1
+
+attributes #0 = { noinline nounwind }
+attributes #1 = { nocallback nofree }
+
+!llvm.dbg.cu = !{!0}
+!1 = !DIFile(filename: "sq.c", directory: "/tmp/build")
+!2 = !DIFile(filename: "/usr/include/stdio.h", directory: "/tmp/build")
+!10 = distinct !DISubprogram(name: "square", scope: !1, file: !1, line: 2)
+!15 = !DILocation(line: 3, column: 14, scope: !10)
+!16 = !DILocation(line: 3, column: 5, scope: !10)
+!17 = distinct !DISubprogram(name: "main", scope: !1, file: !1, line: 5)
+!18 = distinct !DISubprogram(name: "helper", scope: !2, file: !2, line: 40)
+!20 = !DILocation(line: 0, scope: !17)
+!21 = !DILocation(line: 3, column: 14, scope: !10, inlinedAt: !23)
+!22 = !DILocation(line: 41, scope: !18)
+!23 = distinct !DILocation(line: 6, column: 20, scope: !17)
+"#;
+
+    fn texts(lines: &[OutputLine]) -> Vec<(&str, Option<u32>)> {
+        lines
+            .iter()
+            .map(|line| (line.text.as_str(), line.source_line))
+            .collect()
+    }
+
+    #[test]
+    fn filters_assembly() {
+        let lines = filter_asm(GCC_ASM, "sq.c");
+        assert_eq!(
+            texts(&lines),
+            [
+                ("square:", None),
+                ("  imull %edi, %edi", Some(3)),
+                ("  movl %edi, %eax", Some(4)),
+                ("  ret", Some(4)),
+                (".LC0:", None),
+                (r#"  .string "%d\n""#, None),
+                ("main:", None),
+                ("  leaq .LC0(%rip), %rdi", Some(6)),
+                ("  call printf@PLT", Some(6)),
+                ("  movl $0, %eax", None),
+                ("  ret", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn maps_assembly_to_the_source_file_only() {
+        let lines = filter_asm(GCC_ASM, "other.c");
+        assert!(lines.iter().all(|line| line.source_line.is_none()));
+    }
+
+    #[test]
+    fn strips_trailing_comments() {
+        assert_eq!(
+            strip_asm_comment("movl %edi, %eax   # kill: def $eax"),
+            "movl %edi, %eax "
+        );
+        assert_eq!(strip_asm_comment("\tret\t// return"), "\tret");
+        assert_eq!(strip_asm_comment("mov w0, #3"), "mov w0, #3");
+    }
+
+    #[test]
+    fn finds_local_labels() {
+        let labels: Vec<&str> = local_labels("jne .LBB0_2, .L3$x").collect();
+        assert_eq!(labels, [".LBB0_2", ".L3$x"]);
+        assert_eq!(local_labels(".LC0(%rip)").collect::<Vec<_>>(), [".LC0"]);
+    }
+
+    #[test]
+    fn filters_llvm_ir() {
+        let lines = filter_ir(CLANG_IR, "sq.c");
+        assert_eq!(
+            texts(&lines),
+            [
+                ("; ModuleID = 'sq.c'", None),
+                (r#"source_filename = "sq.c""#, None),
+                ("", None),
+                ("define dso_local i32 @square(i32 noundef %0) #0 {", None),
+                ("  %2 = mul nsw i32 %0, %0", Some(3)),
+                ("  ret i32 %2", Some(3)),
+                ("}", None),
+                ("", None),
+                ("define dso_local i32 @main() #0 {", None),
+                // Inlined from `square`, so attributed to the call
+                ("  %1 = mul nsw i32 3, 3", Some(6)),
+                ("  ret i32 0", None),
+                ("}", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_mangled_symbols() {
+        let text = "call _Z6squarei@PLT; _ZN4core3fmt5write17h0123456789abcdefE, __ZN3foo3barEv \
+                    _RNvCs1a2b_7mycrate4main my_Zebra _Zebra _start";
+        let symbols: Vec<&str> = mangled_symbols(text).collect();
+        assert_eq!(
+            symbols,
+            [
+                "_Z6squarei",
+                "_ZN4core3fmt5write17h0123456789abcdefE",
+                "__ZN3foo3barEv",
+                "_RNvCs1a2b_7mycrate4main",
+            ]
+        );
+    }
+
+    #[test]
+    fn strips_rust_hashes() {
+        assert_eq!(
+            strip_rust_hashes("core::fmt::write::h0123456789abcdef"),
+            "core::fmt::write"
+        );
+        assert_eq!(
+            strip_rust_hashes(
+                "<mycrate[1a2b3c4d5e6f7a8b]::Point as core[0f1e2d3c4b5a6978]::fmt::Debug>::fmt"
+            ),
+            "<mycrate::Point as core::fmt::Debug>::fmt"
+        );
+        assert_eq!(
+            strip_rust_hashes("std::vec::Vec<[u8; 4]>"),
+            "std::vec::Vec<[u8; 4]>"
+        );
+        assert_eq!(strip_rust_hashes("foo::h123"), "foo::h123");
+    }
+
+    #[test]
+    fn groups_consecutive_lines_into_ranges() {
+        let line = |source_line| OutputLine {
+            text: String::new(),
+            source_line,
+        };
+        let lines = [
+            line(None),
+            line(Some(3)),
+            line(Some(3)),
+            line(None),
+            line(Some(3)),
+            line(Some(4)),
+        ];
+        let ranges: Vec<(u32, usize, usize)> = line_ranges(&lines)
+            .iter()
+            .map(|range| (range.source_line, range.start, range.end))
+            .collect();
+        assert_eq!(ranges, [(3, 1, 3), (3, 4, 5), (4, 5, 6)]);
+    }
+}
@@ -0,0 +1,106 @@
+use std::path::Path;
+use std::time::Duration;
+use tauri::{AppHandle, State};
+
+use crate::execution::AppState;
+use crate::process;
+use crate::toolchains::{self, Toolchain};
+
+/// Each formatter is killed past this.
+const FORMAT_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// A formatter that reads code on stdin and writes it formatted to stdout.
+/// `{file}` stands for the name the code would have, so the formatter picks
+/// the right dialect and the project's config for it.
+struct Formatter {
+    argv: &'static [&'static str],
+}
+
+/// Formatters per toolchain, in order of preference. The first installed
+/// one is used.
+fn formatters(toolchain: &str) -> &'static [Formatter] {
+    match toolchain {
+        "rust" => &[Formatter {
+            argv: &["rustfmt", "--edition", "2021", "--emit", "stdout"],
+        }],
+        "go" => &[Formatter { argv: &["gofmt"] }],
+        "python" => &[
+            Formatter {
+                argv: &["ruff", "format", "--stdin-filename", "{file}", "-"],
+            },
+            Formatter {
+                argv: &["black", "--quiet", "--stdin-filename", "{file}", "-"],
+            },
+        ],
+        "c" | "c++" => &[Formatter {
+            argv: &["clang-format", "--assume-filename={file}"],
+        }],
+        "javascript" | "typescript" => &[Formatter {
+            argv: &["prettier", "--stdin-filepath", "{file}"],
+        }],
+        _ => &[],
+    }
+}
+
+/// Formats `code` with the first installed formatter for the toolchain.
+/// Formatters run in the project directory, so its config (rustfmt.toml,
+/// .clang-format, .prettierrc, pyproject.toml) applies.
+pub async fn format(toolchain: &Toolchain, code: &str, cwd: &Path) -> Result<String, String> {
+    let candidates = formatters(&toolchain.name);
+    if candidates.is_empty() {
+        return Err(format!("No formatter is known for {}", toolchain.name));
+    }
+
+    let installed = candidates.iter().find_map(|formatter| {
+        toolchains::find_program(formatter.argv[0]).map(|program| (formatter, program))
+    });
+    let Some((formatter, program)) = installed else {
+        let names: Vec<&str> = candidates.iter().map(|f| f.argv[0]).collect();
+        return Err(format!(
+            "Formatter not installed: install {} to format {} code",
+            names.join(" or "),
+            toolchain.name
+        ));
+    };
+
+    let file = format!("main.{}", toolchain.extension);
+    let mut cmd = tokio::process::Command::new(program);
+    cmd.args(
+        formatter.argv[1..]
+            .iter()
+            .map(|arg| arg.replace("{file}", &file)),
+    )
+    .current_dir(cwd);
+
+    let output = process::output(cmd, Some(code.as_bytes()), FORMAT_TIMEOUT)
+        .await
+        .map_err(|e| format!("{} failed: {}", formatter.argv[0], e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!(
+            "{} could not format the code: {}",
+            formatter.argv[0],
+            stderr.trim()
+        ));
+    }
+    String::from_utf8(output.stdout)
+        .map_err(|_| format!("{} produced invalid UTF-8", formatter.argv[0]))
+}
+
+/// Returns `code` formatted by the language's formatter (rustfmt, gofmt,
+/// ruff or black, clang-format, prettier), or an error naming the formatter
+/// to install.
+#[tauri::command]
+pub async fn format_code(
+    app: AppHandle,
+    language: String,
+    code: String,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    let cwd = state.cwd.lock().map_err(|e| e.to_string())?.clone();
+    let toolchains = toolchains::load(&app, &cwd)?;
+    let toolchain = toolchains
+        .find(&language)
+        .ok_or_else(|| format!("Unsupported language for formatting: {}", language))?;
+    format(toolchain, &code, &cwd).await
+}
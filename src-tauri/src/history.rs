@@ -0,0 +1,70 @@
+use std::path::{Path, PathBuf};
+use tauri::State;
+
+use crate::execution::AppState;
+use crate::settings;
+
+const HISTORY_FILE: &str = "terminal_history";
+
+/// Oldest entries are dropped past this many.
+const MAX_HISTORY: usize = 1000;
+
+/// History lives in the opened project's `.pseudoide` directory, one
+/// command per line, wherever the terminal has `cd`ed to since.
+fn history_path(state: &AppState) -> Result<PathBuf, String> {
+    let root = state.project_root.lock().map_err(|e| e.to_string())?;
+    Ok(root.join(settings::SETTINGS_DIR).join(HISTORY_FILE))
+}
+
+fn load(path: &Path) -> Result<Vec<String>, String> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read terminal history: {}", e))?;
+    Ok(content.lines().map(|line| line.to_string()).collect())
+}
+
+/// The project's terminal history, oldest first.
+#[tauri::command]
+pub fn get_terminal_history(state: State<'_, AppState>) -> Result<Vec<String>, String> {
+    load(&history_path(&state)?)
+}
+
+/// Appends a command to the project's history, unless it repeats the last
+/// one.
+#[tauri::command]
+pub fn add_terminal_history(command: String, state: State<'_, AppState>) -> Result<(), String> {
+    let command = command.trim();
+    if command.is_empty() || command.contains('\n') {
+        return Ok(());
+    }
+
+    let path = history_path(&state)?;
+    let mut history = load(&path)?;
+    if history.last().map(String::as_str) == Some(command) {
+        return Ok(());
+    }
+    history.push(command.to_string());
+    if history.len() > MAX_HISTORY {
+        history.drain(..history.len() - MAX_HISTORY);
+    }
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let mut content = history.join("\n");
+    content.push('\n');
+    std::fs::write(&path, content).map_err(|e| format!("Failed to write terminal history: {}", e))
+}
+
+#[tauri::command]
+pub fn clear_terminal_history(state: State<'_, AppState>) -> Result<(), String> {
+    match std::fs::remove_file(history_path(&state)?) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(format!("Failed to clear terminal history: {}", e))
+        }
+        _ => Ok(()),
+    }
+}
@@ -4,9 +4,31 @@ fn greet(name: &str) -> String {
     format!("Hello, {}! You've been greeted from Rust!", name)
 }
 
+mod analysis;
+mod benchmark;
 mod commands;
+mod compile_cache;
+mod completion;
+mod coverage;
+mod debugger;
+mod diagnostics;
 pub mod execution;
+mod explorer;
+mod formatter;
+mod history;
+mod limits;
 mod llm;
+mod process;
+mod projects;
+mod run_config;
+mod sandbox;
+mod sanitizers;
+mod settings;
+mod shell;
+mod terminal;
+mod testing;
+mod toolchains;
+mod workspace;
 
 use std::sync::Mutex;
 use tauri::Manager;
@@ -19,10 +41,31 @@ pub fn run() {
         .manage(llm::LLMState {
             server_process: Mutex::new(None),
         })
-        .manage(execution::AppState {
-            cwd: Mutex::new(
-                std::env::current_dir().unwrap_or_else(|_| std::path::PathBuf::from(".")),
-            ),
+        .manage({
+            let cwd = std::env::current_dir().unwrap_or_else(|_| std::path::PathBuf::from("."));
+            execution::AppState {
+                cwd: Mutex::new(cwd.clone()),
+                project_root: Mutex::new(cwd),
+            }
+        })
+        .manage(execution::RunRegistry::default())
+        .manage(execution::TerminalCommands::default())
+        .manage(shell::ShellSessions::default())
+        .manage(terminal::TerminalSessions::default())
+        .manage(debugger::DebugSessions::default())
+        .setup(|app| {
+            let builds = app.path().app_cache_dir()?.join("builds");
+            app.manage(workspace::BuildWorkspaces::new(builds));
+            let compile_cache = app.path().app_cache_dir()?.join("compile-cache");
+            app.manage(compile_cache::CompileCache::new(compile_cache));
+            Ok(())
+        })
+        .on_window_event(|window, event| {
+            if let tauri::WindowEvent::Destroyed = event {
+                window
+                    .state::<terminal::TerminalSessions>()
+                    .close_window(window.label());
+            }
         })
         .invoke_handler(tauri::generate_handler![
             greet,
@@ -35,11 +78,53 @@ pub fn run() {
             llm::generate_code,
             llm::chat_inference,
             execution::execute_code,
+            benchmark::benchmark_code,
+            compile_cache::get_compile_cache_info,
+            compile_cache::clear_compile_cache,
+            execution::write_stdin,
+            execution::close_stdin,
+            execution::kill_execution,
+            execution::execution_status,
             execution::run_terminal_command,
+            execution::interrupt_terminal_command,
+            history::get_terminal_history,
+            history::add_terminal_history,
+            history::clear_terminal_history,
+            completion::complete_terminal_input,
+            terminal::open_terminal,
+            terminal::write_terminal,
+            terminal::resize_terminal,
+            terminal::close_terminal,
+            terminal::list_terminals,
+            analysis::analyze_code,
+            formatter::format_code,
+            explorer::explore_code,
+            debugger::start_debug_session,
+            debugger::debug_set_breakpoint,
+            debugger::debug_remove_breakpoint,
+            debugger::debug_breakpoints,
+            debugger::debug_continue,
+            debugger::debug_step_over,
+            debugger::debug_step_into,
+            debugger::debug_step_out,
+            debugger::debug_pause,
+            debugger::debug_stack,
+            debugger::debug_locals,
+            debugger::debug_write_input,
+            debugger::stop_debug_session,
             execution::change_working_directory,
             execution::ensure_testing_grounds,
             execution::write_file,
             execution::get_default_projects_path,
+            run_config::list_run_configs,
+            run_config::save_run_config,
+            run_config::delete_run_config,
+            settings::get_project_settings,
+            settings::save_project_settings,
+            testing::list_test_cases,
+            testing::save_test_cases,
+            testing::run_tests,
+            toolchains::list_toolchains,
         ])
         .build(tauri::generate_context!())
         .expect("error while building tauri application")
@@ -50,6 +135,11 @@ pub fn run() {
                 if let Some(mut child) = process.take() {
                     let _ = child.kill();
                 }
+
+                app_handle.state::<execution::RunRegistry>().kill_all();
+                app_handle.state::<terminal::TerminalSessions>().close_all();
+                app_handle.state::<debugger::DebugSessions>().close_all();
+                app_handle.state::<workspace::BuildWorkspaces>().cleanup();
             }
         });
 }
@@ -0,0 +1,148 @@
+use serde::{Deserialize, Serialize};
+use std::process::ExitStatus;
+
+use crate::execution::StopReason;
+
+/// Resource limits for programs started from the IDE, set per project under
+/// `[limits]` in the settings file. They are installed with setrlimit in the
+/// child just before exec, so they are only enforced on Unix. A value of 0
+/// leaves that resource unlimited.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ResourceLimits {
+    /// CPU time in seconds (RLIMIT_CPU).
+    pub cpu_secs: u64,
+    /// Heap and data segment size in MiB (RLIMIT_DATA). Unlike RLIMIT_AS
+    /// this ignores address space that runtimes like V8 and Go only reserve.
+    pub memory_mb: u64,
+    /// Largest file the program may write, in MiB (RLIMIT_FSIZE).
+    pub file_size_mb: u64,
+    /// Processes for the current user (RLIMIT_NPROC). This counts processes
+    /// outside the IDE too, so keep it well above what the desktop uses.
+    pub max_processes: u64,
+}
+
+impl Default for ResourceLimits {
+    fn default() -> Self {
+        Self {
+            cpu_secs: 60,
+            memory_mb: 4096,
+            file_size_mb: 1024,
+            max_processes: 4096,
+        }
+    }
+}
+
+// Messages runtimes print when they abort on a refused allocation
+const MEMORY_MARKERS: &[&str] = &["std::bad_alloc", "memory allocation of", "out of memory"];
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+type Resource = libc::__rlimit_resource_t;
+#[cfg(all(unix, not(all(target_os = "linux", target_env = "gnu"))))]
+type Resource = libc::c_int;
+
+impl ResourceLimits {
+    /// Installs the limits on `cmd`, to take effect in the spawned child.
+    #[cfg(unix)]
+    pub fn apply(&self, cmd: &mut std::process::Command) {
+        use std::os::unix::process::CommandExt;
+
+        const MIB: u64 = 1024 * 1024;
+        let limits = self.clone();
+
+        // Only setrlimit runs between fork and exec, which is async-signal-safe
+        unsafe {
+            cmd.pre_exec(move || {
+                if limits.cpu_secs > 0 {
+                    // The soft limit raises SIGXCPU, the hard one a second later SIGKILL
+                    set_rlimit(libc::RLIMIT_CPU, limits.cpu_secs, limits.cpu_secs + 1)?;
+                }
+                if limits.memory_mb > 0 {
+                    let bytes = limits.memory_mb * MIB;
+                    set_rlimit(libc::RLIMIT_DATA, bytes, bytes)?;
+                }
+                if limits.file_size_mb > 0 {
+                    let bytes = limits.file_size_mb * MIB;
+                    set_rlimit(libc::RLIMIT_FSIZE, bytes, bytes)?;
+                }
+                if limits.max_processes > 0 {
+                    set_rlimit(
+                        libc::RLIMIT_NPROC,
+                        limits.max_processes,
+                        limits.max_processes,
+                    )?;
+                }
+                Ok(())
+            });
+        }
+    }
+
+    /// Works out whether one of the limits is why the process died. CPU and
+    /// file size limits are signalled by the kernel. The memory limit only
+    /// shows up as a failed allocation, so it is recognised by the abort
+    /// that follows together with the runtime's message on stderr. A refused
+    /// fork leaves no trace like that, so the process limit is never
+    /// reported. `through_shell` is set when `status` is a shell's, which
+    /// reports a child's fatal signal as exit code 128 + signo.
+    #[cfg(unix)]
+    pub fn exceeded(
+        &self,
+        status: &ExitStatus,
+        stderr: &str,
+        through_shell: bool,
+    ) -> Option<StopReason> {
+        use std::os::unix::process::ExitStatusExt;
+
+        let signal = status.signal().or_else(|| {
+            status
+                .code()
+                .filter(|code| through_shell && *code > 128)
+                .map(|code| code - 128)
+        });
+
+        match signal? {
+            libc::SIGXCPU if self.cpu_secs > 0 => Some(StopReason::CpuLimit),
+            libc::SIGXFSZ if self.file_size_mb > 0 => Some(StopReason::FileSizeLimit),
+            libc::SIGABRT
+                if self.memory_mb > 0 && MEMORY_MARKERS.iter().any(|m| stderr.contains(m)) =>
+            {
+                Some(StopReason::MemoryLimit)
+            }
+            _ => None,
+        }
+    }
+
+    #[cfg(not(unix))]
+    pub fn exceeded(
+        &self,
+        _status: &ExitStatus,
+        _stderr: &str,
+        _through_shell: bool,
+    ) -> Option<StopReason> {
+        None
+    }
+}
+
+/// Lowers a limit, never raising it past the hard limit the IDE itself runs
+/// under (which would fail with EPERM for unprivileged users).
+#[cfg(unix)]
+fn set_rlimit(resource: Resource, soft: u64, hard: u64) -> std::io::Result<()> {
+    let mut current = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    if unsafe { libc::getrlimit(resource, &mut current) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    let hard = (hard as libc::rlim_t).min(current.rlim_max);
+    let limit = libc::rlimit {
+        rlim_cur: (soft as libc::rlim_t).min(hard),
+        rlim_max: hard,
+    };
+    if unsafe { libc::setrlimit(resource, &limit) } == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error())
+    }
+}
@@ -10,6 +10,9 @@ use std::sync::Mutex;
 use tauri::{AppHandle, Emitter, Manager, State};
 use zip::ZipArchive;
 
+use crate::execution::AppState;
+use crate::{formatter, settings, toolchains};
+
 pub struct LLMState {
     pub server_process: Mutex<Option<Child>>,
 }
@@ -200,6 +203,8 @@ pub fn load_model(app: AppHandle, state: State<LLMState>) -> Result<String, Stri
 pub struct GenerationResponse {
     language: String,
     code: String,
+    /// The code went through the language's formatter.
+    formatted: bool,
 }
 
 #[derive(serde::Deserialize)]
@@ -252,8 +257,15 @@ pub async fn chat_inference(history: Vec<ChatMessage>) -> Result<String, String>
     Ok(content)
 }
 
+/// Transcribes pseudocode with the local model. Unless the project turns
+/// `formatting.on_generate` off, the result is formatted when a formatter
+/// for its language is installed.
 #[tauri::command]
-pub async fn generate_code(prompt: String) -> Result<GenerationResponse, String> {
+pub async fn generate_code(
+    app: AppHandle,
+    prompt: String,
+    state: State<'_, AppState>,
+) -> Result<GenerationResponse, String> {
     let client = reqwest::Client::new();
 
     // Construct ChatML prompt for Qwen
@@ -307,5 +319,27 @@ pub async fn generate_code(prompt: String) -> Result<GenerationResponse, String>
         }
     }
 
-    Ok(GenerationResponse { language, code })
+    let cwd = state.cwd.lock().map_err(|e| e.to_string())?.clone();
+    let mut formatted = false;
+    if settings::load(&cwd)
+        .unwrap_or_default()
+        .formatting
+        .on_generate
+    {
+        // Broken toolchain config, a missing formatter or code it can't
+        // parse all leave the code as it is
+        let toolchains = toolchains::load(&app, &cwd).ok();
+        if let Some(toolchain) = toolchains.as_ref().and_then(|t| t.find(&language)) {
+            if let Ok(pretty) = formatter::format(toolchain, &code, &cwd).await {
+                code = pretty;
+                formatted = true;
+            }
+        }
+    }
+
+    Ok(GenerationResponse {
+        language,
+        code,
+        formatted,
+    })
 }
@@ -0,0 +1,275 @@
+use std::future::Future;
+#[cfg(target_os = "windows")]
+use std::os::windows::process::CommandExt;
+use std::pin::Pin;
+use std::process::{ExitStatus, Stdio};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::{ChildStderr, ChildStdin, ChildStdout};
+
+/// How a process ended.
+pub struct ProcessExit {
+    pub status: ExitStatus,
+    /// Peak resident memory in KiB, where the platform reports it.
+    pub peak_memory_kb: Option<u64>,
+    /// Whether the process was killed for running past its timeout.
+    pub timed_out: bool,
+}
+
+type ExitFuture = Pin<Box<dyn Future<Output = std::io::Result<ProcessExit>> + Send>>;
+
+/// A spawned child with piped stdout and stderr. Take the pipes before
+/// calling `wait`, which consumes the process.
+///
+/// On Unix the child leads its own process group, so `kill_tree` also
+/// reaches anything it spawned, and it is reaped with wait4 to get its
+/// resource usage.
+pub struct Process {
+    pub pid: u32,
+    pub stdin: Option<ChildStdin>,
+    pub stdout: Option<ChildStdout>,
+    pub stderr: Option<ChildStderr>,
+    exit: ExitFuture,
+}
+
+impl Process {
+    /// Spawns `cmd`. Its stdin is piped when `piped_stdin` is set and
+    /// connected to /dev/null otherwise.
+    #[cfg(unix)]
+    pub fn spawn(mut cmd: tokio::process::Command, piped_stdin: bool) -> std::io::Result<Self> {
+        use std::os::unix::process::CommandExt;
+
+        // Spawned through std so tokio never reaps the child before wait4 can
+        let mut child = cmd
+            .as_std_mut()
+            .process_group(0)
+            .stdin(if piped_stdin {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        let pid = child.id();
+        let stdin = child.stdin.take().map(ChildStdin::from_std).transpose()?;
+        let stdout = child.stdout.take().map(ChildStdout::from_std).transpose()?;
+        let stderr = child.stderr.take().map(ChildStderr::from_std).transpose()?;
+
+        let exit = Box::pin(async move {
+            tokio::task::spawn_blocking(move || wait4(pid))
+                .await
+                .map_err(std::io::Error::other)?
+        });
+
+        Ok(Self {
+            pid,
+            stdin,
+            stdout,
+            stderr,
+            exit,
+        })
+    }
+
+    #[cfg(not(unix))]
+    pub fn spawn(mut cmd: tokio::process::Command, piped_stdin: bool) -> std::io::Result<Self> {
+        let mut child = cmd
+            .stdin(if piped_stdin {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+
+        let pid = child.id().unwrap_or_default();
+        let stdin = child.stdin.take();
+        let stdout = child.stdout.take();
+        let stderr = child.stderr.take();
+
+        let exit = Box::pin(async move {
+            child.wait().await.map(|status| ProcessExit {
+                status,
+                peak_memory_kb: None,
+                timed_out: false,
+            })
+        });
+
+        Ok(Self {
+            pid,
+            stdin,
+            stdout,
+            stderr,
+            exit,
+        })
+    }
+
+    /// Waits for the process to exit, killing it (and its group) once
+    /// `timeout` elapses.
+    pub async fn wait(mut self, timeout: Option<Duration>) -> std::io::Result<ProcessExit> {
+        let Some(limit) = timeout else {
+            return self.exit.await;
+        };
+
+        match tokio::time::timeout(limit, &mut self.exit).await {
+            Ok(exit) => exit,
+            Err(_) => {
+                kill_tree(self.pid)?;
+                let mut exit = self.exit.await?;
+                exit.timed_out = true;
+                Ok(exit)
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+fn wait4(pid: u32) -> std::io::Result<ProcessExit> {
+    use std::os::unix::process::ExitStatusExt;
+
+    let mut status = 0;
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    while unsafe { libc::wait4(pid as libc::pid_t, &mut status, 0, &mut usage) } < 0 {
+        let err = std::io::Error::last_os_error();
+        if err.kind() != std::io::ErrorKind::Interrupted {
+            return Err(err);
+        }
+    }
+
+    // ru_maxrss is in KiB on Linux but in bytes on macOS
+    #[cfg(target_os = "macos")]
+    let peak_memory_kb = usage.ru_maxrss as u64 / 1024;
+    #[cfg(not(target_os = "macos"))]
+    let peak_memory_kb = usage.ru_maxrss as u64;
+
+    Ok(ProcessExit {
+        status: ExitStatus::from_raw(status),
+        peak_memory_kb: Some(peak_memory_kb),
+        timed_out: false,
+    })
+}
+
+/// Runs `cmd` to completion with `input` on its stdin (or /dev/null) and
+/// returns what it printed. It is killed once `timeout` elapses. For tools
+/// the IDE runs itself, like linters and formatters, not user programs.
+pub async fn output(
+    mut cmd: tokio::process::Command,
+    input: Option<&[u8]>,
+    timeout: Duration,
+) -> Result<std::process::Output, String> {
+    cmd.stdin(if input.is_some() {
+        Stdio::piped()
+    } else {
+        Stdio::null()
+    })
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .kill_on_drop(true);
+    #[cfg(target_os = "windows")]
+    cmd.creation_flags(0x08000000);
+
+    let mut child = cmd.spawn().map_err(|e| e.to_string())?;
+    if let (Some(mut stdin), Some(input)) = (child.stdin.take(), input) {
+        // Written alongside the reads, so a tool that answers before it has
+        // read everything can't fill its stdout pipe and stall
+        let input = input.to_vec();
+        tauri::async_runtime::spawn(async move {
+            let _ = stdin.write_all(&input).await;
+        });
+    }
+
+    match tokio::time::timeout(timeout, child.wait_with_output()).await {
+        Ok(output) => output.map_err(|e| e.to_string()),
+        Err(_) => Err(format!("timed out after {} s", timeout.as_secs())),
+    }
+}
+
+/// Signal that terminated the process, if any.
+#[cfg(unix)]
+pub fn exit_signal(status: &ExitStatus) -> Option<i32> {
+    use std::os::unix::process::ExitStatusExt;
+    status.signal()
+}
+
+#[cfg(not(unix))]
+pub fn exit_signal(_status: &ExitStatus) -> Option<i32> {
+    None
+}
+
+/// Kills a process spawned by `Process::spawn` along with its children.
+#[cfg(unix)]
+pub fn kill_tree(pid: u32) -> std::io::Result<()> {
+    signal_group(pid, libc::SIGKILL)
+}
+
+/// Sends SIGINT to a process spawned by `Process::spawn` and its children,
+/// as Ctrl+C in a terminal would.
+#[cfg(unix)]
+pub fn interrupt_tree(pid: u32) -> std::io::Result<()> {
+    signal_group(pid, libc::SIGINT)
+}
+
+/// Whether a process with this pid exists.
+#[cfg(unix)]
+pub fn is_running(pid: u32) -> bool {
+    // Signal 0 only checks; EPERM means it exists but belongs to someone else
+    let found = unsafe { libc::kill(pid as libc::pid_t, 0) } == 0;
+    found || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// Without a cheap way to check on Windows, every pid counts as running.
+#[cfg(not(unix))]
+pub fn is_running(_pid: u32) -> bool {
+    true
+}
+
+/// Kills just the process `pid`, which may be in any process group.
+#[cfg(unix)]
+pub fn kill(pid: u32) -> std::io::Result<()> {
+    send_signal(pid as libc::pid_t, libc::SIGKILL)
+}
+
+#[cfg(unix)]
+fn signal_group(pid: u32, signal: libc::c_int) -> std::io::Result<()> {
+    // Children were spawned as process group leaders, so -pid is the group
+    send_signal(-(pid as libc::pid_t), signal)
+}
+
+#[cfg(unix)]
+fn send_signal(pid: libc::pid_t, signal: libc::c_int) -> std::io::Result<()> {
+    if unsafe { libc::kill(pid, signal) } == 0 {
+        return Ok(());
+    }
+
+    // The process may already be gone by the time a signal is asked for
+    let err = std::io::Error::last_os_error();
+    if err.raw_os_error() == Some(libc::ESRCH) {
+        Ok(())
+    } else {
+        Err(err)
+    }
+}
+
+#[cfg(target_os = "windows")]
+pub fn kill_tree(pid: u32) -> std::io::Result<()> {
+    std::process::Command::new("taskkill")
+        .args(["/T", "/F", "/PID", &pid.to_string()])
+        .creation_flags(0x08000000)
+        .status()
+        .map(|_| ())
+}
+
+#[cfg(target_os = "windows")]
+pub fn kill(pid: u32) -> std::io::Result<()> {
+    kill_tree(pid)
+}
+
+/// Windows has no SIGINT for processes without a console, so this kills
+/// the tree instead.
+#[cfg(target_os = "windows")]
+pub fn interrupt_tree(pid: u32) -> std::io::Result<()> {
+    kill_tree(pid)
+}
@@ -0,0 +1,324 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+use crate::diagnostics::DiagnosticFormat;
+use crate::settings;
+use crate::toolchains::Toolchain;
+
+/// Build system found in a project root, in the order they are checked.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProjectKind {
+    Cargo,
+    GoModule,
+    Npm,
+    Python,
+    #[serde(rename = "cmake")]
+    CMake,
+    Make,
+}
+
+impl ProjectKind {
+    const ALL: [ProjectKind; 6] = [
+        ProjectKind::Cargo,
+        ProjectKind::GoModule,
+        ProjectKind::Npm,
+        ProjectKind::Python,
+        ProjectKind::CMake,
+        ProjectKind::Make,
+    ];
+
+    /// The file whose presence marks the project.
+    pub fn marker(&self) -> &'static str {
+        match self {
+            ProjectKind::Cargo => "Cargo.toml",
+            ProjectKind::GoModule => "go.mod",
+            ProjectKind::Npm => "package.json",
+            ProjectKind::Python => "pyproject.toml",
+            ProjectKind::CMake => "CMakeLists.txt",
+            ProjectKind::Make => "Makefile",
+        }
+    }
+
+    /// Toolchains (by name) whose code this kind of project builds.
+    fn languages(&self) -> &'static [&'static str] {
+        match self {
+            ProjectKind::Cargo => &["rust"],
+            ProjectKind::GoModule => &["go"],
+            ProjectKind::Npm => &["javascript", "typescript"],
+            ProjectKind::Python => &["python"],
+            ProjectKind::CMake | ProjectKind::Make => &["c", "c++"],
+        }
+    }
+}
+
+/// Commands that build and run a whole project. Build commands run in the
+/// project root; run commands use absolute paths so they work from any
+/// working directory (though `npm start` and `make run` still run the
+/// program in the project root).
+pub struct ProjectBuild {
+    pub kind: ProjectKind,
+    /// Build commands, run in order; empty when there is nothing to build.
+    pub compile: Vec<Vec<String>>,
+    run: Vec<String>,
+    args: ArgStyle,
+    /// Extra environment for the run.
+    pub env: Vec<(String, String)>,
+    pub diagnostics: Option<DiagnosticFormat>,
+}
+
+/// How program arguments are passed through the run command.
+enum ArgStyle {
+    Append,
+    /// After a `--`, for `cargo run` and `npm start`.
+    AfterSeparator,
+    /// As `ARGS="..."` with each argument shell-quoted, for the Makefile's run
+    /// target to use.
+    MakeVariable,
+}
+
+impl ProjectBuild {
+    pub fn run_argv(&self, args: &[String]) -> Vec<String> {
+        let mut argv = self.run.clone();
+        if args.is_empty() {
+            return argv;
+        }
+
+        match self.args {
+            ArgStyle::Append => argv.extend(args.iter().cloned()),
+            ArgStyle::AfterSeparator => {
+                argv.push("--".to_string());
+                argv.extend(args.iter().cloned());
+            }
+            ArgStyle::MakeVariable => {
+                let quoted: Vec<String> = args.iter().map(|arg| make_quote(arg)).collect();
+                argv.push(format!("ARGS={}", quoted.join(" ")));
+            }
+        }
+        argv
+    }
+}
+
+/// Looks for a build system in `project_dir` that handles the code of
+/// `toolchain`. `None` means the code should be run as a single file,
+/// as it is when the build system is there but doesn't say what to run.
+/// `build_dir` is the run's scratch directory, for outputs that don't
+/// belong in the project.
+pub fn detect(
+    project_dir: &Path,
+    toolchain: &Toolchain,
+    build_dir: &Path,
+) -> Result<Option<ProjectBuild>, String> {
+    let Some(kind) = ProjectKind::ALL.into_iter().find(|kind| {
+        kind.languages()
+            .iter()
+            .any(|l| l.eq_ignore_ascii_case(&toolchain.name))
+            && project_dir.join(kind.marker()).is_file()
+    }) else {
+        return Ok(None);
+    };
+
+    let build = match kind {
+        ProjectKind::Cargo => Some(ProjectBuild {
+            kind,
+            compile: vec![argv(&["cargo", "build"])],
+            run: argv(&[
+                "cargo",
+                "run",
+                "--quiet",
+                "--manifest-path",
+                &project_dir.join(kind.marker()).to_string_lossy(),
+            ]),
+            args: ArgStyle::AfterSeparator,
+            env: Vec::new(),
+            diagnostics: None,
+        }),
+        ProjectKind::GoModule => {
+            let binary = build_dir.join(executable("main"));
+            Some(ProjectBuild {
+                kind,
+                compile: vec![argv(&["go", "build", "-o", &binary.to_string_lossy(), "."])],
+                run: vec![binary.to_string_lossy().into_owned()],
+                args: ArgStyle::Append,
+                env: Vec::new(),
+                diagnostics: Some(DiagnosticFormat::Go),
+            })
+        }
+        ProjectKind::Npm => Some(npm_build(project_dir)?),
+        ProjectKind::Python => python_build(project_dir, toolchain)?,
+        ProjectKind::CMake => cmake_build(project_dir)?,
+        ProjectKind::Make => make_build(project_dir)?,
+    };
+
+    Ok(build)
+}
+
+/// Quotes an argument for the shell that runs the Makefile's recipe, with
+/// `$` doubled so make doesn't expand it first.
+fn make_quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', "'\\''").replace('$', "$$"))
+}
+
+fn argv(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+fn executable(name: &str) -> String {
+    if cfg!(target_os = "windows") {
+        format!("{}.exe", name)
+    } else {
+        name.to_string()
+    }
+}
+
+fn read_marker(project_dir: &Path, kind: ProjectKind) -> Result<String, String> {
+    std::fs::read_to_string(project_dir.join(kind.marker()))
+        .map_err(|e| format!("Failed to read {}: {}", kind.marker(), e))
+}
+
+fn npm_build(project_dir: &Path) -> Result<ProjectBuild, String> {
+    let manifest: serde_json::Value =
+        serde_json::from_str(&read_marker(project_dir, ProjectKind::Npm)?)
+            .map_err(|e| format!("Invalid package.json: {}", e))?;
+    let has_script = |name: &str| manifest["scripts"][name].is_string();
+
+    // npm is a batch script on Windows, which Command won't resolve by name
+    let npm = if cfg!(target_os = "windows") {
+        "npm.cmd"
+    } else {
+        "npm"
+    };
+
+    let compile = if has_script("build") {
+        vec![argv(&[npm, "run", "build", "--silent"])]
+    } else {
+        Vec::new()
+    };
+    let (run, args) = if has_script("start") {
+        let prefix = project_dir.to_string_lossy();
+        (
+            argv(&[npm, "--prefix", &prefix, "start", "--silent"]),
+            ArgStyle::AfterSeparator,
+        )
+    } else {
+        let main = project_dir.join(manifest["main"].as_str().unwrap_or("index.js"));
+        (argv(&["node", &main.to_string_lossy()]), ArgStyle::Append)
+    };
+
+    Ok(ProjectBuild {
+        kind: ProjectKind::Npm,
+        compile,
+        run,
+        args,
+        env: Vec::new(),
+        diagnostics: None,
+    })
+}
+
+/// Runs the package named in `pyproject.toml` as a module when it has a
+/// `__main__.py` (flat or `src/` layout), and `main.py` otherwise, with the
+/// interpreter the Python toolchain runs single files with.
+fn python_build(project_dir: &Path, toolchain: &Toolchain) -> Result<Option<ProjectBuild>, String> {
+    let manifest: toml::Table = toml::from_str(&read_marker(project_dir, ProjectKind::Python)?)
+        .map_err(|e| format!("Invalid pyproject.toml: {}", e))?;
+    let package = manifest
+        .get("project")
+        .and_then(|project| project.get("name"))
+        .and_then(|name| name.as_str())
+        .map(|name| name.replace('-', "_"));
+
+    // The package's parent goes on PYTHONPATH so `-m` finds it from any
+    // working directory
+    let package_root = package.as_ref().and_then(|package| {
+        [project_dir.to_path_buf(), project_dir.join("src")]
+            .into_iter()
+            .find(|root| root.join(package).join("__main__.py").is_file())
+    });
+    let main = project_dir.join("main.py");
+    let python = toolchain.run_program()?;
+
+    let (run, env) = match (package, package_root) {
+        (Some(package), Some(root)) => (
+            argv(&[&python, "-m", &package]),
+            vec![(
+                "PYTHONPATH".to_string(),
+                root.to_string_lossy().into_owned(),
+            )],
+        ),
+        _ if main.is_file() => (argv(&[&python, &main.to_string_lossy()]), Vec::new()),
+        // Neither a package with __main__.py nor a main.py to run
+        _ => return Ok(None),
+    };
+
+    Ok(Some(ProjectBuild {
+        kind: ProjectKind::Python,
+        compile: Vec::new(),
+        run,
+        args: ArgStyle::Append,
+        env,
+        diagnostics: None,
+    }))
+}
+
+/// Configures into `.pseudoide/build` so incremental builds survive between
+/// runs, and runs the first `add_executable` target.
+fn cmake_build(project_dir: &Path) -> Result<Option<ProjectBuild>, String> {
+    let lists = read_marker(project_dir, ProjectKind::CMake)?;
+    let Some(mut target) = first_argument(&lists, "add_executable(") else {
+        return Ok(None);
+    };
+    if target == "${PROJECT_NAME}" {
+        target = first_argument(&lists, "project(").unwrap_or(target);
+    }
+
+    let out_dir: PathBuf = project_dir.join(settings::SETTINGS_DIR).join("build");
+    let out = out_dir.to_string_lossy();
+
+    Ok(Some(ProjectBuild {
+        kind: ProjectKind::CMake,
+        compile: vec![
+            argv(&["cmake", "-S", ".", "-B", &out]),
+            argv(&["cmake", "--build", &out]),
+        ],
+        run: vec![out_dir
+            .join(executable(&target))
+            .to_string_lossy()
+            .into_owned()],
+        args: ArgStyle::Append,
+        env: Vec::new(),
+        diagnostics: Some(DiagnosticFormat::Gcc),
+    }))
+}
+
+/// First argument of the first `command(` call in a CMake file.
+fn first_argument(lists: &str, command: &str) -> Option<String> {
+    let start = lists.to_ascii_lowercase().find(command)? + command.len();
+    lists[start..]
+        .split(|c: char| c.is_whitespace() || c == ')')
+        .find(|token| !token.is_empty())
+        .map(|token| token.to_string())
+}
+
+/// Builds the default target and runs `make run`; a Makefile gives no other
+/// way to know what to execute. Program arguments are passed as `ARGS`.
+fn make_build(project_dir: &Path) -> Result<Option<ProjectBuild>, String> {
+    let makefile = read_marker(project_dir, ProjectKind::Make)?;
+    if !makefile.lines().any(|line| line.starts_with("run:")) {
+        return Ok(None);
+    }
+
+    Ok(Some(ProjectBuild {
+        kind: ProjectKind::Make,
+        compile: vec![argv(&["make"])],
+        run: argv(&[
+            "make",
+            "-C",
+            &project_dir.to_string_lossy(),
+            "--silent",
+            "run",
+        ]),
+        args: ArgStyle::MakeVariable,
+        env: Vec::new(),
+        diagnostics: Some(DiagnosticFormat::Gcc),
+    }))
+}
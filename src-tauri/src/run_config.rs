@@ -0,0 +1,144 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tauri::State;
+
+use crate::execution::AppState;
+use crate::settings;
+use crate::toolchains::BuildProfile;
+
+const RUN_CONFIGS_FILE: &str = "run_configs.toml";
+
+/// How a program is invoked by `execute_code`. Everything is optional; the
+/// default runs the program with no arguments in the project root.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct RunConfig {
+    pub args: Vec<String>,
+    /// Added to (or overriding) the inherited environment.
+    pub env: BTreeMap<String, String>,
+    /// Fed to the program, after which its stdin is closed. Without it the
+    /// terminal can write to the program with `write_stdin`.
+    pub stdin: Option<StdinSource>,
+    /// Directory the program runs in, relative to the project root.
+    pub working_dir: Option<PathBuf>,
+    /// Build profile for compiled single files. Defaults to the project's
+    /// `execution.build_profile`.
+    pub profile: Option<BuildProfile>,
+    /// Measures which lines of a single file run, into the result's
+    /// `coverage`.
+    pub coverage: bool,
+    /// Builds just the file even in a detected project. Set by test runs,
+    /// which check the code in the editor.
+    #[serde(skip)]
+    pub single_file: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum StdinSource {
+    Text(String),
+    /// A fixture file, relative to the project root.
+    File(PathBuf),
+}
+
+impl RunConfig {
+    pub fn working_dir(&self, project_dir: &Path) -> Result<PathBuf, String> {
+        let Some(dir) = &self.working_dir else {
+            return Ok(project_dir.to_path_buf());
+        };
+
+        let dir = project_dir.join(dir);
+        if !dir.is_dir() {
+            return Err(format!("Working directory {:?} does not exist", dir));
+        }
+        Ok(dir)
+    }
+
+    pub fn stdin_bytes(&self, project_dir: &Path) -> Result<Option<Vec<u8>>, String> {
+        match &self.stdin {
+            None => Ok(None),
+            Some(StdinSource::Text(text)) => Ok(Some(text.clone().into_bytes())),
+            Some(StdinSource::File(path)) => {
+                let path = project_dir.join(path);
+                std::fs::read(&path)
+                    .map(Some)
+                    .map_err(|e| format!("Failed to read stdin fixture {:?}: {}", path, e))
+            }
+        }
+    }
+}
+
+/// Named run configurations, stored in `.pseudoide/run_configs.toml`.
+#[derive(Serialize, Deserialize, Default)]
+struct RunConfigFile {
+    #[serde(default, rename = "config")]
+    configs: BTreeMap<String, RunConfig>,
+}
+
+fn configs_path(project_dir: &Path) -> PathBuf {
+    project_dir
+        .join(settings::SETTINGS_DIR)
+        .join(RUN_CONFIGS_FILE)
+}
+
+fn load(project_dir: &Path) -> Result<RunConfigFile, String> {
+    let path = configs_path(project_dir);
+    if !path.exists() {
+        return Ok(RunConfigFile::default());
+    }
+
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read run configurations: {}", e))?;
+    toml::from_str(&content).map_err(|e| format!("Invalid run configurations in {:?}: {}", path, e))
+}
+
+fn store(project_dir: &Path, file: &RunConfigFile) -> Result<(), String> {
+    let path = configs_path(project_dir);
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+
+    let content = toml::to_string_pretty(file).map_err(|e| e.to_string())?;
+    std::fs::write(&path, content).map_err(|e| format!("Failed to write run configurations: {}", e))
+}
+
+/// A saved run configuration, by name.
+pub fn find(project_dir: &Path, name: &str) -> Result<RunConfig, String> {
+    load(project_dir)?
+        .configs
+        .remove(name)
+        .ok_or_else(|| format!("No run configuration named '{}'", name))
+}
+
+#[tauri::command]
+pub fn list_run_configs(state: State<'_, AppState>) -> Result<BTreeMap<String, RunConfig>, String> {
+    let cwd = state.cwd.lock().map_err(|e| e.to_string())?.clone();
+    Ok(load(&cwd)?.configs)
+}
+
+#[tauri::command]
+pub fn save_run_config(
+    name: String,
+    config: RunConfig,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("Run configuration name cannot be empty".to_string());
+    }
+
+    let cwd = state.cwd.lock().map_err(|e| e.to_string())?.clone();
+    let mut file = load(&cwd)?;
+    file.configs.insert(name, config);
+    store(&cwd, &file)
+}
+
+#[tauri::command]
+pub fn delete_run_config(name: String, state: State<'_, AppState>) -> Result<(), String> {
+    let cwd = state.cwd.lock().map_err(|e| e.to_string())?.clone();
+    let mut file = load(&cwd)?;
+    if file.configs.remove(&name).is_none() {
+        return Err(format!("No run configuration named '{}'", name));
+    }
+    store(&cwd, &file)
+}
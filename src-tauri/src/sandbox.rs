@@ -0,0 +1,192 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// How programs started by `execute_code` are run. `Trusted` spawns them
+/// directly; `Sandbox` puts the run phase into fresh user, mount and network
+/// namespaces where only the project directory is writable.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecutionMode {
+    #[default]
+    Trusted,
+    Sandbox,
+}
+
+/// Namespace sandbox for one project directory. Everything the child needs
+/// between fork and exec is prepared up front, since allocating there is not
+/// safe in a multithreaded parent.
+#[cfg(target_os = "linux")]
+#[derive(Clone)]
+pub struct Sandbox {
+    project_dir: std::ffi::CString,
+    /// False when the project itself lives under /tmp, which a fresh tmpfs
+    /// there would hide.
+    scratch_tmp: bool,
+    uid_map: String,
+    gid_map: String,
+}
+
+#[cfg(target_os = "linux")]
+impl Sandbox {
+    pub fn new(project_dir: &Path) -> Result<Self, String> {
+        use std::os::unix::ffi::OsStrExt;
+
+        let project_dir = std::fs::canonicalize(project_dir)
+            .map_err(|e| format!("Failed to resolve project directory: {}", e))?;
+        let scratch_tmp = !project_dir.starts_with("/tmp");
+        let project_dir = std::ffi::CString::new(project_dir.as_os_str().as_bytes())
+            .map_err(|_| "Project path contains a NUL byte".to_string())?;
+
+        // Map ourselves onto the same ids inside the namespace
+        let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+
+        Ok(Self {
+            project_dir,
+            scratch_tmp,
+            uid_map: format!("{} {} 1\n", uid, uid),
+            gid_map: format!("{} {} 1\n", gid, gid),
+        })
+    }
+
+    /// Arranges for `cmd` to enter the sandbox right before it execs.
+    pub fn apply(&self, cmd: &mut std::process::Command) {
+        use std::os::unix::process::CommandExt;
+
+        // The host's caches are read-only in here; keep Go's build cache on the scratch /tmp
+        cmd.env("TMPDIR", "/tmp").env("GOCACHE", "/tmp/go-build");
+
+        let sandbox = self.clone();
+        unsafe {
+            cmd.pre_exec(move || sandbox.enter());
+        }
+    }
+
+    fn enter(&self) -> std::io::Result<()> {
+        check(unsafe {
+            libc::unshare(libc::CLONE_NEWUSER | libc::CLONE_NEWNS | libc::CLONE_NEWNET)
+        })?;
+
+        // setgroups must be denied before an unprivileged gid_map write;
+        // kernels older than 3.19 do not have the file at all
+        match write_proc(c"/proc/self/setgroups", b"deny") {
+            Err(e) if e.raw_os_error() != Some(libc::ENOENT) => return Err(e),
+            _ => {}
+        }
+        write_proc(c"/proc/self/uid_map", self.uid_map.as_bytes())?;
+        write_proc(c"/proc/self/gid_map", self.gid_map.as_bytes())?;
+
+        // Keep every mount change below out of the host's namespace
+        mount(None, c"/", None, libc::MS_REC | libc::MS_PRIVATE)?;
+
+        // Bind mounts give the project and /dev their own mount points, so
+        // they can be made writable again after the rest goes read-only
+        let project = self.project_dir.as_c_str();
+        mount(Some(project), project, None, libc::MS_BIND | libc::MS_REC)?;
+        mount(Some(c"/dev"), c"/dev", None, libc::MS_BIND | libc::MS_REC)?;
+
+        set_readonly(c"/", true)?;
+        set_readonly(project, false)?;
+        set_readonly(c"/dev", false)?;
+
+        // Scratch space that disappears with the namespace
+        if self.scratch_tmp {
+            mount(
+                Some(c"tmpfs"),
+                c"/tmp",
+                Some(c"tmpfs"),
+                libc::MS_NOSUID | libc::MS_NODEV,
+            )?;
+        }
+
+        // The inherited cwd still points at the original, now read-only,
+        // mount; resolving its path again lands on the bind mount instead
+        let mut cwd = [0 as libc::c_char; libc::PATH_MAX as usize];
+        if unsafe { libc::getcwd(cwd.as_mut_ptr(), cwd.len()) }.is_null() {
+            return Err(std::io::Error::last_os_error());
+        }
+        check(unsafe { libc::chdir(cwd.as_ptr()) })
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+#[derive(Clone)]
+pub struct Sandbox;
+
+#[cfg(not(target_os = "linux"))]
+impl Sandbox {
+    pub fn new(_project_dir: &Path) -> Result<Self, String> {
+        Err("Sandboxed execution is only available on Linux".to_string())
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn check(ret: libc::c_int) -> std::io::Result<()> {
+    if ret == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error())
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn write_proc(path: &std::ffi::CStr, data: &[u8]) -> std::io::Result<()> {
+    let fd = unsafe { libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC) };
+    if fd < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    let written = unsafe { libc::write(fd, data.as_ptr().cast(), data.len()) };
+    let result = if written == data.len() as isize {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error())
+    };
+
+    unsafe { libc::close(fd) };
+    result
+}
+
+#[cfg(target_os = "linux")]
+fn mount(
+    source: Option<&std::ffi::CStr>,
+    target: &std::ffi::CStr,
+    fstype: Option<&std::ffi::CStr>,
+    flags: libc::c_ulong,
+) -> std::io::Result<()> {
+    check(unsafe {
+        libc::mount(
+            source.map_or(std::ptr::null(), |s| s.as_ptr()),
+            target.as_ptr(),
+            fstype.map_or(std::ptr::null(), |s| s.as_ptr()),
+            flags,
+            std::ptr::null(),
+        )
+    })
+}
+
+/// Sets or clears read-only on the mount at `target` and everything below it.
+#[cfg(target_os = "linux")]
+fn set_readonly(target: &std::ffi::CStr, readonly: bool) -> std::io::Result<()> {
+    let attr = libc::mount_attr {
+        attr_set: if readonly { libc::MOUNT_ATTR_RDONLY } else { 0 },
+        attr_clr: if readonly { 0 } else { libc::MOUNT_ATTR_RDONLY },
+        propagation: 0,
+        userns_fd: 0,
+    };
+
+    let ret = unsafe {
+        libc::syscall(
+            libc::SYS_mount_setattr,
+            libc::AT_FDCWD,
+            target.as_ptr(),
+            libc::AT_RECURSIVE as libc::c_uint,
+            &attr as *const libc::mount_attr,
+            std::mem::size_of::<libc::mount_attr>(),
+        )
+    };
+    if ret == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error())
+    }
+}
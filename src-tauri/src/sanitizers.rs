@@ -0,0 +1,384 @@
+use serde::Serialize;
+use std::path::Path;
+
+use crate::diagnostics::parse_location;
+
+/// A problem reported at runtime by a sanitizer or by one of Rust's checks,
+/// parsed from the program's stderr.
+#[derive(Serialize, Debug, Clone)]
+pub struct SanitizerFinding {
+    /// `address`, `leak`, `undefined`, `thread` or `memory` for the
+    /// sanitizers, `rust` for Rust's overflow and bounds checks.
+    pub sanitizer: String,
+    /// What went wrong, e.g. `heap-buffer-overflow`, `direct-leak`,
+    /// `undefined-behavior` or `arithmetic-overflow`.
+    pub kind: String,
+    pub message: String,
+    /// Where in the code it happened: the innermost frame in the source
+    /// file, or the location the report names.
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+    /// The report's first stack trace, innermost frame first.
+    pub stack: Vec<SanitizerFrame>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SanitizerFrame {
+    pub function: Option<String>,
+    pub file: Option<String>,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+}
+
+/// Where a report stands while its lines are read.
+enum Trace {
+    /// Frames go into the current finding's stack.
+    Collecting,
+    /// The first trace is over; later ones (e.g. where a block was
+    /// allocated) are skipped.
+    Done,
+}
+
+/// Finds sanitizer reports and Rust check panics in `stderr`. `source` is
+/// the file the code was compiled from, whose frames give a finding's
+/// location.
+pub fn parse(stderr: &str, source: Option<&Path>) -> Vec<SanitizerFinding> {
+    let source_name = source
+        .and_then(|s| s.file_name())
+        .map(|n| n.to_string_lossy());
+    let mut findings: Vec<SanitizerFinding> = Vec::new();
+    let mut trace = Trace::Done;
+    let mut lines = stderr.lines().peekable();
+
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim();
+
+        if let Some(frame) = trimmed.strip_prefix('#').and_then(parse_frame) {
+            if let (Trace::Collecting, Some(finding)) = (&trace, findings.last_mut()) {
+                finding.stack.push(frame);
+            }
+            continue;
+        }
+        if trimmed.is_empty() {
+            if let (Trace::Collecting, Some(finding)) = (&trace, findings.last()) {
+                if !finding.stack.is_empty() {
+                    trace = Trace::Done;
+                }
+            }
+            continue;
+        }
+
+        if let Some((sanitizer, message)) = sanitizer_error(trimmed) {
+            // LeakSanitizer's header only announces the leaks that follow
+            if sanitizer != "leak" {
+                let kind = message.split_whitespace().next().unwrap_or("error");
+                // The registers that follow are of no use to the reader
+                let message = message.split(" at pc ").next().unwrap_or(message);
+                findings.push(finding(sanitizer, kind, message));
+                trace = Trace::Collecting;
+            }
+        } else if let Some(kind) = leak_kind(trimmed) {
+            let message = trimmed.trim_end_matches(':');
+            findings.push(finding("leak", kind, message));
+            trace = Trace::Collecting;
+        } else if let Some((location, message)) = trimmed.split_once(": runtime error: ") {
+            let mut found = finding("undefined", "undefined-behavior", message);
+            if let Some((_, line, column)) = parse_location(location) {
+                found.line = Some(line);
+                found.column = column;
+            }
+            findings.push(found);
+            trace = Trace::Collecting;
+        } else if let Some(rest) = trimmed
+            .strip_prefix("thread '")
+            .and_then(|rest| rest.split_once(" panicked at "))
+            .map(|(_, rest)| rest)
+        {
+            let (location, message) = match rest.strip_prefix('\'') {
+                // Before Rust 1.73: panicked at 'message', file:line:col
+                Some(quoted) => match quoted.rsplit_once("', ") {
+                    Some((message, location)) => (location, message.to_string()),
+                    None => continue,
+                },
+                None => (
+                    rest.trim_end_matches(':'),
+                    lines.next().unwrap_or_default().trim().to_string(),
+                ),
+            };
+            if let Some(kind) = rust_check(&message) {
+                let mut found = finding("rust", kind, &message);
+                let (file, line, column) = match parse_location(location) {
+                    Some((file, line, column)) => (file, Some(line), column),
+                    None => (location.to_string(), None, None),
+                };
+                found.stack.push(SanitizerFrame {
+                    function: None,
+                    file: Some(file),
+                    line,
+                    column,
+                });
+                findings.push(found);
+                trace = Trace::Done;
+            }
+        }
+    }
+
+    for finding in &mut findings {
+        let in_source = finding.stack.iter().find(|frame| {
+            let name = frame
+                .file
+                .as_deref()
+                .and_then(|file| Path::new(file).file_name())
+                .map(|n| n.to_string_lossy());
+            name.is_some() && name == source_name
+        });
+        if let Some(frame) = in_source {
+            finding.line = frame.line;
+            finding.column = frame.column;
+        }
+    }
+    findings
+}
+
+fn finding(sanitizer: &str, kind: &str, message: &str) -> SanitizerFinding {
+    SanitizerFinding {
+        sanitizer: sanitizer.to_string(),
+        kind: kind.to_string(),
+        message: message.to_string(),
+        line: None,
+        column: None,
+        stack: Vec::new(),
+    }
+}
+
+/// `==123==ERROR: AddressSanitizer: heap-buffer-overflow on address ...`
+/// gives `("address", "heap-buffer-overflow on address ...")`.
+fn sanitizer_error(line: &str) -> Option<(&'static str, &str)> {
+    let rest = line.split_once("ERROR: ")?.1;
+    let (tool, message) = rest.split_once("Sanitizer: ")?;
+    let sanitizer = match tool {
+        "Address" => "address",
+        "Leak" => "leak",
+        "UndefinedBehavior" => "undefined",
+        "Thread" => "thread",
+        "Memory" => "memory",
+        _ => return None,
+    };
+    Some((sanitizer, message))
+}
+
+/// `Direct leak of 40 byte(s) in 1 object(s) allocated from:`
+fn leak_kind(line: &str) -> Option<&'static str> {
+    if line.starts_with("Direct leak of ") {
+        Some("direct-leak")
+    } else if line.starts_with("Indirect leak of ") {
+        Some("indirect-leak")
+    } else {
+        None
+    }
+}
+
+/// The panics of Rust's runtime checks, which the sanitize profile turns on
+/// in full. Other panics are the program's own.
+fn rust_check(message: &str) -> Option<&'static str> {
+    if message.starts_with("attempt to divide by zero")
+        || message.starts_with("attempt to calculate the remainder with a divisor of zero")
+    {
+        Some("division-by-zero")
+    } else if message.starts_with("attempt to") && message.ends_with("with overflow") {
+        Some("arithmetic-overflow")
+    } else if message.starts_with("index out of bounds") {
+        Some("index-out-of-bounds")
+    } else {
+        None
+    }
+}
+
+/// A stack frame without its `#`, as sanitizers print them:
+/// `0 0x55d1c6b4a1f5 in main /tmp/build/main.c:6:5`, or with the module
+/// and offset in parentheses instead of a source location.
+fn parse_frame(frame: &str) -> Option<SanitizerFrame> {
+    let (index, rest) = frame.split_once(' ')?;
+    if !index.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let rest = rest.trim_start();
+    let (address, rest) = rest.split_once(' ').unwrap_or((rest, ""));
+    if !address.starts_with("0x") {
+        return None;
+    }
+
+    let rest = rest.trim();
+    let rest = rest.strip_prefix("in ").unwrap_or(rest);
+    // Demangled C++ names contain spaces, so the location is taken from the
+    // end
+    let (function, location) = match rest.rsplit_once(' ') {
+        Some((function, location)) => (Some(function.trim()), location),
+        None if rest.starts_with('(') => (None, rest),
+        None => (Some(rest), ""),
+    };
+
+    let (file, line, column) = if location.starts_with('(') || location.is_empty() {
+        (None, None, None)
+    } else {
+        match parse_location(location) {
+            Some((file, line, column)) => (Some(file), Some(line), column),
+            None => (Some(location.to_string()), None, None),
+        }
+    };
+
+    Some(SanitizerFrame {
+        function: function.filter(|f| !f.is_empty()).map(String::from),
+        file,
+        line,
+        column,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEAP_OVERFLOW: &str = "\
+=================================================================
+==32142==ERROR: AddressSanitizer: heap-buffer-overflow on address 0x602000000020 at pc 0x55ce10aea1ce bp 0x7ffd8c157750 sp 0x7ffd8c157748
+WRITE of size 4 at 0x602000000020 thread T0
+    #0 0x55ce10aea1cd in main /tmp/build/main.c:4
+    #1 0x7f422d045249  (/lib/x86_64-linux-gnu/libc.so.6+0x27249)
+    #2 0x7f422d045304 in __libc_start_main (/lib/x86_64-linux-gnu/libc.so.6+0x27304)
+    #3 0x55ce10aea0b0 in _start (/tmp/build/main+0x10b0)
+
+0x602000000020 is located 0 bytes to the right of 16-byte region [0x602000000010,0x602000000020)
+allocated by thread T0 here:
+    #0 0x7f422d2b89cf in __interceptor_malloc ../../../../src/libsanitizer/asan/asan_malloc_linux.cpp:69
+    #1 0x55ce10aea18a in main /tmp/build/main.c:3
+
+SUMMARY: AddressSanitizer: heap-buffer-overflow /tmp/build/main.c:4 in main
+";
+
+    const LEAK: &str = "\
+=================================================================
+==32149==ERROR: LeakSanitizer: detected memory leaks
+
+Direct leak of 40 byte(s) in 1 object(s) allocated from:
+    #0 0x7f5a376b89cf in __interceptor_malloc ../../../../src/libsanitizer/asan/asan_malloc_linux.cpp:69
+    #1 0x5635d7e6616a in main /tmp/build/leak.c:3:15
+    #2 0x7f5a37445249  (/lib/x86_64-linux-gnu/libc.so.6+0x27249)
+
+Indirect leak of 8 byte(s) in 1 object(s) allocated from:
+    #0 0x7f5a376b89cf in __interceptor_malloc ../../../../src/libsanitizer/asan/asan_malloc_linux.cpp:69
+
+SUMMARY: AddressSanitizer: 48 byte(s) leaked in 2 allocation(s).
+";
+
+    #[test]
+    fn parses_address_sanitizer_reports() {
+        let findings = parse(HEAP_OVERFLOW, Some(Path::new("/project/main.c")));
+        assert_eq!(findings.len(), 1);
+        let finding = &findings[0];
+        assert_eq!(finding.sanitizer, "address");
+        assert_eq!(finding.kind, "heap-buffer-overflow");
+        assert_eq!(
+            finding.message,
+            "heap-buffer-overflow on address 0x602000000020"
+        );
+        assert_eq!((finding.line, finding.column), (Some(4), None));
+
+        // Only the first trace, not where the block was allocated
+        assert_eq!(finding.stack.len(), 4);
+        assert_eq!(finding.stack[0].function.as_deref(), Some("main"));
+        assert_eq!(finding.stack[1].function, None);
+        assert_eq!(finding.stack[1].file, None);
+        assert_eq!(
+            finding.stack[2].function.as_deref(),
+            Some("__libc_start_main")
+        );
+    }
+
+    #[test]
+    fn parses_each_leak() {
+        let findings = parse(LEAK, Some(Path::new("leak.c")));
+        let kinds: Vec<&str> = findings.iter().map(|f| f.kind.as_str()).collect();
+        assert_eq!(kinds, ["direct-leak", "indirect-leak"]);
+        assert!(findings.iter().all(|f| f.sanitizer == "leak"));
+        assert_eq!(
+            findings[0].message,
+            "Direct leak of 40 byte(s) in 1 object(s) allocated from"
+        );
+        assert_eq!((findings[0].line, findings[0].column), (Some(3), Some(15)));
+        assert_eq!(findings[0].stack.len(), 3);
+        // No frame in the source, so no location
+        assert_eq!(findings[1].line, None);
+    }
+
+    #[test]
+    fn parses_undefined_behavior() {
+        let findings = parse(
+            "main.c:4:7: runtime error: signed integer overflow: 2147483647 + 1 cannot be represented in type 'int'\n",
+            None,
+        );
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].sanitizer, "undefined");
+        assert_eq!(findings[0].kind, "undefined-behavior");
+        assert!(findings[0].message.starts_with("signed integer overflow"));
+        assert_eq!((findings[0].line, findings[0].column), (Some(4), Some(7)));
+    }
+
+    #[test]
+    fn parses_rust_check_panics() {
+        let findings = parse(
+            "thread 'main' panicked at src/main.rs:5:13:\nattempt to add with overflow\nnote: run with `RUST_BACKTRACE=1`\n",
+            Some(Path::new("main.rs")),
+        );
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].sanitizer, "rust");
+        assert_eq!(findings[0].kind, "arithmetic-overflow");
+        assert_eq!((findings[0].line, findings[0].column), (Some(5), Some(13)));
+
+        // The format before Rust 1.73
+        let findings = parse(
+            "thread 'main' panicked at 'index out of bounds: the len is 3 but the index is 5', src/main.rs:9:5\n",
+            Some(Path::new("main.rs")),
+        );
+        assert_eq!(findings[0].kind, "index-out-of-bounds");
+        assert_eq!(findings[0].line, Some(9));
+
+        let findings = parse(
+            "thread 'main' panicked at src/main.rs:2:5:\nattempt to divide by zero\n",
+            None,
+        );
+        assert_eq!(findings[0].kind, "division-by-zero");
+    }
+
+    #[test]
+    fn ignores_the_programs_own_panics() {
+        let stderr = "thread 'main' panicked at src/main.rs:2:5:\nsomething went wrong\n";
+        assert!(parse(stderr, None).is_empty());
+        assert!(parse("plain output\n#include <stdio.h>\n", None).is_empty());
+    }
+
+    #[test]
+    fn parses_frames() {
+        let frame = parse_frame("0 0x55d1c6b4a1f5 in main /tmp/build/main.c:6:5").unwrap();
+        assert_eq!(frame.function.as_deref(), Some("main"));
+        assert_eq!(frame.file.as_deref(), Some("/tmp/build/main.c"));
+        assert_eq!((frame.line, frame.column), (Some(6), Some(5)));
+
+        let frame = parse_frame(
+            "1 0x55d1c6b4a2a0 in std::vector<int, std::allocator<int> >::at(unsigned long) /tmp/v.cpp:12",
+        )
+        .unwrap();
+        assert_eq!(
+            frame.function.as_deref(),
+            Some("std::vector<int, std::allocator<int> >::at(unsigned long)")
+        );
+        assert_eq!(frame.line, Some(12));
+
+        let frame = parse_frame("3 0x55ce10aea0b0 in _start (/tmp/build/main+0x10b0)").unwrap();
+        assert_eq!(frame.function.as_deref(), Some("_start"));
+        assert_eq!(frame.file, None);
+
+        assert!(parse_frame("include <stdio.h>").is_none());
+        assert!(parse_frame("1 not-an-address").is_none());
+    }
+}
@@ -0,0 +1,106 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tauri::State;
+
+use crate::execution::AppState;
+use crate::limits::ResourceLimits;
+use crate::sandbox::ExecutionMode;
+use crate::toolchains::BuildProfile;
+
+pub const SETTINGS_DIR: &str = ".pseudoide";
+const SETTINGS_FILE: &str = "settings.toml";
+
+/// Per-project settings, stored in `.pseudoide/settings.toml` in the project
+/// root. Missing files and missing keys fall back to the defaults below.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct ProjectSettings {
+    pub execution: ExecutionSettings,
+    pub limits: ResourceLimits,
+    pub formatting: FormattingSettings,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ExecutionSettings {
+    /// Wall-clock limit for the run phase, in seconds. 0 disables it.
+    pub timeout_secs: u64,
+    pub mode: ExecutionMode,
+    /// How many finished runs keep their build directory (source, binary
+    /// and compiler output) until the app exits. 0 deletes it right away.
+    pub retain_builds: usize,
+    /// Build and run the whole project with its build system (Cargo, go.mod,
+    /// package.json, ...) when one matches the language, rather than just
+    /// the code in the editor.
+    pub detect_projects: bool,
+    /// Reuse the binary of an earlier build of the same code with the same
+    /// compiler, instead of compiling again. Single files only.
+    pub compile_cache: bool,
+    /// Build profile for compiled single files, unless the run
+    /// configuration names one. Toolchains without it build as standard.
+    pub build_profile: BuildProfile,
+}
+
+impl Default for ExecutionSettings {
+    fn default() -> Self {
+        Self {
+            timeout_secs: 60,
+            mode: ExecutionMode::Trusted,
+            retain_builds: 0,
+            detect_projects: true,
+            compile_cache: true,
+            build_profile: BuildProfile::Standard,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct FormattingSettings {
+    /// Run generated code through the language's formatter before it is
+    /// returned. Code is left as generated when no formatter is installed.
+    pub on_generate: bool,
+}
+
+impl Default for FormattingSettings {
+    fn default() -> Self {
+        Self { on_generate: true }
+    }
+}
+
+fn settings_path(project_dir: &Path) -> PathBuf {
+    project_dir.join(SETTINGS_DIR).join(SETTINGS_FILE)
+}
+
+pub fn load(project_dir: &Path) -> Result<ProjectSettings, String> {
+    let path = settings_path(project_dir);
+    if !path.exists() {
+        return Ok(ProjectSettings::default());
+    }
+
+    let content =
+        std::fs::read_to_string(&path).map_err(|e| format!("Failed to read settings: {}", e))?;
+    toml::from_str(&content).map_err(|e| format!("Invalid settings in {:?}: {}", path, e))
+}
+
+#[tauri::command]
+pub fn get_project_settings(state: State<'_, AppState>) -> Result<ProjectSettings, String> {
+    let cwd = state.cwd.lock().map_err(|e| e.to_string())?.clone();
+    load(&cwd)
+}
+
+#[tauri::command]
+pub fn save_project_settings(
+    settings: ProjectSettings,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let cwd = state.cwd.lock().map_err(|e| e.to_string())?.clone();
+    let path = settings_path(&cwd);
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+
+    let content = toml::to_string_pretty(&settings).map_err(|e| e.to_string())?;
+    std::fs::write(&path, content).map_err(|e| format!("Failed to write settings: {}", e))
+}
@@ -0,0 +1,671 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Session used when a terminal doesn't name one.
+pub const DEFAULT_SESSION: &str = "default";
+
+/// Shell state that outlives a single `run_terminal_command` call: the
+/// previous directory for `cd -`, the `pushd` stack and exported variables.
+/// Each command still runs in a fresh shell, so this is what carries over
+/// between lines. The working directory itself is `AppState.cwd`.
+#[derive(Default)]
+pub struct ShellSessions {
+    sessions: Mutex<HashMap<String, ShellSession>>,
+}
+
+#[derive(Default)]
+struct ShellSession {
+    oldpwd: Option<PathBuf>,
+    dir_stack: Vec<PathBuf>,
+    /// Exported variables; `None` marks one that was unset.
+    env: BTreeMap<String, Option<String>>,
+}
+
+/// How a command depends on the one before it on the same line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Connector {
+    /// First on the line, or after `;`.
+    Always,
+    /// After `&&`: runs if the previous command succeeded.
+    And,
+    /// After `||`: runs if the previous command failed.
+    Or,
+}
+
+impl Connector {
+    pub fn should_run(&self, previous_succeeded: bool) -> bool {
+        match self {
+            Connector::Always => true,
+            Connector::And => previous_succeeded,
+            Connector::Or => !previous_succeeded,
+        }
+    }
+}
+
+pub struct BuiltinOutcome {
+    pub output: String,
+    pub success: bool,
+}
+
+impl BuiltinOutcome {
+    fn ok(output: String) -> Self {
+        Self {
+            output,
+            success: true,
+        }
+    }
+
+    fn error(output: String) -> Self {
+        Self {
+            output,
+            success: false,
+        }
+    }
+}
+
+pub const BUILTINS: [&str; 6] = ["cd", "pushd", "popd", "dirs", "export", "unset"];
+
+impl ShellSessions {
+    /// Runs `command` if it is a builtin, updating `cwd` and the session.
+    /// `None` means it is not one and should go to the shell.
+    pub fn run_builtin(
+        &self,
+        session: &str,
+        command: &str,
+        cwd: &Mutex<PathBuf>,
+    ) -> Result<Option<BuiltinOutcome>, String> {
+        let name = command.split_whitespace().next().unwrap_or_default();
+        if !BUILTINS.contains(&name) {
+            return Ok(None);
+        }
+
+        let mut sessions = self.sessions.lock().map_err(|e| e.to_string())?;
+        let shell = sessions.entry(session.to_string()).or_default();
+        let mut cwd = cwd.lock().map_err(|e| e.to_string())?;
+
+        let words = match split_words(command, |var| shell.var(var, &cwd)) {
+            Ok(words) => words,
+            Err(e) => return Ok(Some(BuiltinOutcome::error(format!("{}: {}", name, e)))),
+        };
+        let args = words.get(1..).unwrap_or_default();
+
+        let outcome = match name {
+            "cd" => shell.cd(&mut cwd, args),
+            "pushd" => shell.pushd(&mut cwd, args),
+            "popd" => shell.popd(&mut cwd),
+            "dirs" => BuiltinOutcome::ok(shell.dirs(&cwd)),
+            "export" => shell.export(args),
+            _ => shell.unset(args),
+        };
+        Ok(Some(outcome))
+    }
+
+    /// Variables to set (`Some`) and remove (`None`) for commands run in
+    /// the session.
+    pub fn env(&self, session: &str) -> Vec<(String, Option<String>)> {
+        let Ok(sessions) = self.sessions.lock() else {
+            return Vec::new();
+        };
+        let Some(shell) = sessions.get(session) else {
+            return Vec::new();
+        };
+
+        let mut env: Vec<(String, Option<String>)> = shell
+            .env
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        if let Some(oldpwd) = &shell.oldpwd {
+            env.push((
+                "OLDPWD".to_string(),
+                Some(oldpwd.to_string_lossy().into_owned()),
+            ));
+        }
+        env
+    }
+}
+
+impl ShellSession {
+    fn var(&self, name: &str, cwd: &Path) -> Option<String> {
+        match name {
+            "PWD" => return Some(cwd.to_string_lossy().into_owned()),
+            "OLDPWD" => {
+                return self
+                    .oldpwd
+                    .as_ref()
+                    .map(|p| p.to_string_lossy().into_owned())
+            }
+            _ => {}
+        }
+        match self.env.get(name) {
+            Some(value) => value.clone(),
+            None => std::env::var(name).ok(),
+        }
+    }
+
+    /// Moves to `target`, remembering where we were for `cd -`.
+    fn change_dir(&mut self, cwd: &mut PathBuf, target: &Path) -> Result<(), String> {
+        let path = std::fs::canonicalize(cwd.join(target))
+            .map_err(|e| format!("{}: {}", target.display(), e))?;
+        if !path.is_dir() {
+            return Err(format!("{}: Not a directory", target.display()));
+        }
+
+        self.oldpwd = Some(std::mem::replace(cwd, path));
+        Ok(())
+    }
+
+    fn cd(&mut self, cwd: &mut PathBuf, args: &[String]) -> BuiltinOutcome {
+        let (target, announce) = match args {
+            [] => match dirs::home_dir() {
+                Some(home) => (home, false),
+                None => return BuiltinOutcome::error("cd: HOME not set".to_string()),
+            },
+            [dash] if dash == "-" => match &self.oldpwd {
+                // Like bash, `cd -` prints the directory it lands in
+                Some(oldpwd) => (oldpwd.clone(), true),
+                None => return BuiltinOutcome::error("cd: OLDPWD not set".to_string()),
+            },
+            [dir] => (PathBuf::from(dir), false),
+            _ => return BuiltinOutcome::error("cd: too many arguments".to_string()),
+        };
+
+        match self.change_dir(cwd, &target) {
+            Ok(()) if announce => BuiltinOutcome::ok(cwd.to_string_lossy().into_owned()),
+            Ok(()) => BuiltinOutcome::ok(String::new()),
+            Err(e) => BuiltinOutcome::error(format!("cd: {}", e)),
+        }
+    }
+
+    /// `pushd dir` saves the current directory and moves to `dir`; a bare
+    /// `pushd` swaps the current directory with the top of the stack.
+    fn pushd(&mut self, cwd: &mut PathBuf, args: &[String]) -> BuiltinOutcome {
+        let previous = cwd.clone();
+        let result = match args {
+            [] => match self.dir_stack.pop() {
+                Some(top) => {
+                    let result = self.change_dir(cwd, &top);
+                    if result.is_err() {
+                        self.dir_stack.push(top);
+                    }
+                    result
+                }
+                None => return BuiltinOutcome::error("pushd: no other directory".to_string()),
+            },
+            [dir] => self.change_dir(cwd, Path::new(dir)),
+            _ => return BuiltinOutcome::error("pushd: too many arguments".to_string()),
+        };
+
+        match result {
+            Ok(()) => {
+                self.dir_stack.push(previous);
+                BuiltinOutcome::ok(self.dirs(cwd))
+            }
+            Err(e) => BuiltinOutcome::error(format!("pushd: {}", e)),
+        }
+    }
+
+    fn popd(&mut self, cwd: &mut PathBuf) -> BuiltinOutcome {
+        let Some(top) = self.dir_stack.pop() else {
+            return BuiltinOutcome::error("popd: directory stack empty".to_string());
+        };
+
+        match self.change_dir(cwd, &top) {
+            Ok(()) => BuiltinOutcome::ok(self.dirs(cwd)),
+            Err(e) => {
+                self.dir_stack.push(top);
+                BuiltinOutcome::error(format!("popd: {}", e))
+            }
+        }
+    }
+
+    /// The current directory followed by the stack, most recent first, with
+    /// the home directory shortened to `~`.
+    fn dirs(&self, cwd: &Path) -> String {
+        let home = dirs::home_dir();
+        std::iter::once(cwd)
+            .chain(self.dir_stack.iter().rev().map(PathBuf::as_path))
+            .map(
+                |dir| match home.as_ref().and_then(|h| dir.strip_prefix(h).ok()) {
+                    Some(rest) if rest.as_os_str().is_empty() => "~".to_string(),
+                    Some(rest) => format!("~/{}", rest.display()),
+                    None => dir.display().to_string(),
+                },
+            )
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// `export NAME=value ...`; a bare `export` lists the session's
+    /// variables.
+    fn export(&mut self, args: &[String]) -> BuiltinOutcome {
+        if args.is_empty() {
+            let listing = self
+                .env
+                .iter()
+                .filter_map(|(name, value)| {
+                    let value = value.as_ref()?;
+                    Some(format!(
+                        "export {}=\"{}\"",
+                        name,
+                        value.replace('"', "\\\"")
+                    ))
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            return BuiltinOutcome::ok(listing);
+        }
+
+        let mut errors = Vec::new();
+        for arg in args {
+            let (name, value) = match arg.split_once('=') {
+                Some((name, value)) => (name, Some(value)),
+                None => (arg.as_str(), None),
+            };
+            if !is_identifier(name) {
+                errors.push(format!("export: `{}': not a valid identifier", arg));
+                continue;
+            }
+            // Every variable is exported already; `export NAME` alone only
+            // matters to one that was unset
+            match value {
+                Some(value) => {
+                    self.env.insert(name.to_string(), Some(value.to_string()));
+                }
+                None if self.env.get(name) == Some(&None) => {
+                    self.env.insert(name.to_string(), Some(String::new()));
+                }
+                None => {}
+            }
+        }
+
+        if errors.is_empty() {
+            BuiltinOutcome::ok(String::new())
+        } else {
+            BuiltinOutcome::error(errors.join("\n"))
+        }
+    }
+
+    fn unset(&mut self, args: &[String]) -> BuiltinOutcome {
+        for name in args {
+            self.env.insert(name.clone(), None);
+        }
+        BuiltinOutcome::ok(String::new())
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Splits a line at top-level `&&`, `||` and `;`, leaving anything quoted,
+/// escaped or inside parentheses alone. Pipes, redirections and the
+/// commands themselves are left for the shell.
+pub fn split_commands(line: &str) -> Vec<(Connector, String)> {
+    let mut commands = Vec::new();
+    let mut current = String::new();
+    let mut connector = Connector::Always;
+    let mut quote: Option<char> = None;
+    let mut depth = 0usize;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let next = chars.peek().copied();
+        let separator = match (quote, c) {
+            (Some('\''), '\'') | (Some('"'), '"') | (Some('`'), '`') => {
+                quote = None;
+                None
+            }
+            (Some(q), '\\') if q != '\'' => {
+                current.push(c);
+                if let Some(escaped) = chars.next() {
+                    current.push(escaped);
+                }
+                continue;
+            }
+            (Some(_), _) => None,
+            (None, '\\') => {
+                current.push(c);
+                if let Some(escaped) = chars.next() {
+                    current.push(escaped);
+                }
+                continue;
+            }
+            (None, '\'' | '"' | '`') => {
+                quote = Some(c);
+                None
+            }
+            (None, '(') => {
+                depth += 1;
+                None
+            }
+            (None, ')') => {
+                depth = depth.saturating_sub(1);
+                None
+            }
+            (None, '&') if depth == 0 && next == Some('&') => Some(Connector::And),
+            (None, '|') if depth == 0 && next == Some('|') => Some(Connector::Or),
+            (None, ';') if depth == 0 => Some(Connector::Always),
+            _ => None,
+        };
+
+        match separator {
+            Some(separator) => {
+                if separator != Connector::Always {
+                    chars.next();
+                }
+                let command = current.trim();
+                if !command.is_empty() {
+                    commands.push((connector, command.to_string()));
+                }
+                current.clear();
+                connector = separator;
+            }
+            None => current.push(c),
+        }
+    }
+
+    let command = current.trim();
+    if !command.is_empty() {
+        commands.push((connector, command.to_string()));
+    }
+    commands
+}
+
+/// Splits a command into words as a POSIX shell would: quotes are removed,
+/// `$NAME` and `${NAME}` are expanded outside single quotes, and a leading
+/// unquoted `~` becomes the home directory.
+fn split_words(
+    command: &str,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    // Whether a word has begun; `""` is an empty word, not no word
+    let mut started = false;
+    let mut chars = command.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                if started {
+                    words.push(std::mem::take(&mut word));
+                    started = false;
+                }
+            }
+            '\'' => {
+                started = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err("unterminated quote".to_string()),
+                    }
+                }
+            }
+            '"' => {
+                started = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('$' | '`' | '"' | '\\')) => word.push(c),
+                            Some('\n') => {}
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => return Err("unterminated quote".to_string()),
+                        },
+                        Some('$') => word.push_str(&expand_variable(&mut chars, &lookup)),
+                        Some(c) => word.push(c),
+                        None => return Err("unterminated quote".to_string()),
+                    }
+                }
+            }
+            '\\' => {
+                started = true;
+                if let Some(c) = chars.next() {
+                    word.push(c);
+                }
+            }
+            '$' => {
+                // An unquoted expansion to nothing makes no word of its own
+                let value = expand_variable(&mut chars, &lookup);
+                started |= !value.is_empty();
+                word.push_str(&value);
+            }
+            '~' if !started && matches!(chars.peek(), None | Some('/')) => {
+                started = true;
+                match dirs::home_dir() {
+                    Some(home) => word.push_str(&home.to_string_lossy()),
+                    None => word.push('~'),
+                }
+            }
+            c => {
+                started = true;
+                word.push(c);
+            }
+        }
+    }
+
+    if started {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+/// Expands the variable after a `$`. A `$` not followed by a name stays as
+/// it is; unknown variables expand to nothing.
+fn expand_variable(
+    chars: &mut std::iter::Peekable<std::str::Chars>,
+    lookup: &impl Fn(&str) -> Option<String>,
+) -> String {
+    let mut name = String::new();
+    if chars.peek() == Some(&'{') {
+        chars.next();
+        for c in chars.by_ref() {
+            if c == '}' {
+                break;
+            }
+            name.push(c);
+        }
+    } else {
+        while let Some(&c) = chars.peek() {
+            if !(c.is_ascii_alphanumeric() || c == '_') {
+                break;
+            }
+            name.push(c);
+            chars.next();
+        }
+        if name.is_empty() {
+            return "$".to_string();
+        }
+    }
+    lookup(&name).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_commands(line: &str, expected: &[(Connector, &str)]) {
+        let commands = split_commands(line);
+        let commands: Vec<(Connector, &str)> = commands
+            .iter()
+            .map(|(connector, command)| (*connector, command.as_str()))
+            .collect();
+        assert_eq!(commands, expected);
+    }
+
+    fn words(command: &str) -> Result<Vec<String>, String> {
+        split_words(command, |name| match name {
+            "NAME" => Some("bob".to_string()),
+            "SPACED" => Some("a b".to_string()),
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn splits_at_connectors() {
+        assert_commands(
+            "cd src && make || echo failed; ls",
+            &[
+                (Connector::Always, "cd src"),
+                (Connector::And, "make"),
+                (Connector::Or, "echo failed"),
+                (Connector::Always, "ls"),
+            ],
+        );
+        assert_commands(
+            "ls;; pwd;",
+            &[(Connector::Always, "ls"), (Connector::Always, "pwd")],
+        );
+    }
+
+    #[test]
+    fn leaves_quoted_and_grouped_connectors_alone() {
+        assert_commands(
+            r#"echo "a && b" 'c || d'; echo \; done"#,
+            &[
+                (Connector::Always, r#"echo "a && b" 'c || d'"#),
+                (Connector::Always, r"echo \; done"),
+            ],
+        );
+        assert_commands(
+            r#"echo "say \"hi\" && bye" && ls"#,
+            &[
+                (Connector::Always, r#"echo "say \"hi\" && bye""#),
+                (Connector::And, "ls"),
+            ],
+        );
+        assert_commands(
+            "(cd a && make) || true",
+            &[
+                (Connector::Always, "(cd a && make)"),
+                (Connector::Or, "true"),
+            ],
+        );
+        // Pipes and background jobs are the shell's business
+        assert_commands(
+            "ls | grep x & sleep 1",
+            &[(Connector::Always, "ls | grep x & sleep 1")],
+        );
+    }
+
+    #[test]
+    fn connectors_follow_the_previous_result() {
+        assert!(Connector::Always.should_run(false));
+        assert!(Connector::And.should_run(true));
+        assert!(!Connector::And.should_run(false));
+        assert!(Connector::Or.should_run(false));
+        assert!(!Connector::Or.should_run(true));
+    }
+
+    #[test]
+    fn splits_words_and_removes_quotes() {
+        assert_eq!(words(r#"cd "my dir""#).unwrap(), ["cd", "my dir"]);
+        assert_eq!(
+            words(r"echo \$NAME a\ b 'it''s'").unwrap(),
+            ["echo", "$NAME", "a b", "its"]
+        );
+        assert_eq!(
+            words(r#"echo "a\"b" "c\d" "\$NAME" """#).unwrap(),
+            ["echo", "a\"b", r"c\d", "$NAME", ""]
+        );
+    }
+
+    #[test]
+    fn expands_variables_outside_single_quotes() {
+        assert_eq!(
+            words(r#"echo '$NAME' "$NAME" ${NAME}x "$SPACED" $ 5"#).unwrap(),
+            ["echo", "$NAME", "bob", "bobx", "a b", "$", "5"]
+        );
+        // Unknown variables expand to nothing, and unquoted to no word at all
+        assert_eq!(words("cd $UNKNOWN").unwrap(), ["cd"]);
+        assert_eq!(words(r#"cd "$UNKNOWN""#).unwrap(), ["cd", ""]);
+        assert_eq!(words("cd a$UNKNOWN").unwrap(), ["cd", "a"]);
+    }
+
+    #[test]
+    fn expands_a_leading_tilde() {
+        let Some(home) = dirs::home_dir() else {
+            return;
+        };
+        let home = home.to_string_lossy();
+        assert_eq!(
+            words("cd ~/src").unwrap(),
+            ["cd".to_string(), format!("{}/src", home)]
+        );
+        assert_eq!(words("cd ~").unwrap(), ["cd", &*home]);
+        assert_eq!(
+            words(r#"echo a~ "~" ~user"#).unwrap(),
+            ["echo", "a~", "~", "~user"]
+        );
+    }
+
+    #[test]
+    fn rejects_unterminated_quotes() {
+        assert!(words(r#"echo "abc"#).is_err());
+        assert!(words("echo 'abc").is_err());
+        assert!(words(r#"echo "abc\"#).is_err());
+    }
+
+    #[test]
+    fn export_and_unset() {
+        let shells = ShellSessions::default();
+        let cwd = Mutex::new(std::env::temp_dir());
+        let run = |command: &str| shells.run_builtin("test", command, &cwd).unwrap();
+
+        assert!(run("ls -la").is_none());
+        assert!(run("export A=1 B='two words'").unwrap().success);
+        assert!(!run("export 1X=2").unwrap().success);
+        assert!(run("unset A").unwrap().success);
+
+        let env = shells.env("test");
+        assert!(env.contains(&("A".to_string(), None)));
+        assert!(env.contains(&("B".to_string(), Some("two words".to_string()))));
+
+        // Exporting an unset variable brings it back, empty
+        run("export A");
+        assert!(shells
+            .env("test")
+            .contains(&("A".to_string(), Some(String::new()))));
+        assert_eq!(
+            run("export").unwrap().output,
+            "export A=\"\"\nexport B=\"two words\""
+        );
+    }
+
+    #[test]
+    fn cd_pushd_and_popd() {
+        let root = std::env::temp_dir().join(format!("shell-test-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("sub")).unwrap();
+        let root = std::fs::canonicalize(root).unwrap();
+        let sub = root.join("sub");
+
+        let shells = ShellSessions::default();
+        let cwd = Mutex::new(root.clone());
+        let run = |command: &str| shells.run_builtin("test", command, &cwd).unwrap().unwrap();
+
+        assert!(run("cd sub").success);
+        assert_eq!(*cwd.lock().unwrap(), sub);
+        assert_eq!(run("cd -").output, root.to_string_lossy());
+        assert!(!run("cd missing").success);
+        assert!(!run("cd a b").success);
+        assert_eq!(*cwd.lock().unwrap(), root);
+
+        assert!(run("pushd sub").success);
+        assert_eq!(*cwd.lock().unwrap(), sub);
+        assert!(run("popd").success);
+        assert_eq!(*cwd.lock().unwrap(), root);
+        assert!(!run("popd").success);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,263 @@
+use portable_pty::{native_pty_system, ChildKiller, CommandBuilder, MasterPty, PtySize};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, Manager, State, Window};
+
+use crate::execution::AppState;
+
+static NEXT_SESSION_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Interactive shells running in pseudo-terminals, so job control, colour
+/// and full-screen programs work as they would in a real terminal. Each
+/// window has its own set of sessions, by name.
+///
+/// Output is sent to the owning window as `terminal-output` events, and
+/// `terminal-exit` once the shell exits; both carry the session name.
+#[derive(Default)]
+pub struct TerminalSessions {
+    sessions: Mutex<HashMap<(String, String), Session>>,
+}
+
+struct Session {
+    /// Tells this session apart from a later one with the same name, so the
+    /// old one's reader doesn't remove its replacement.
+    id: u64,
+    master: Box<dyn MasterPty + Send>,
+    /// Locked on its own, so a shell that stops reading its input only
+    /// blocks writes to itself.
+    writer: Arc<Mutex<Box<dyn Write + Send>>>,
+    killer: Box<dyn ChildKiller + Send + Sync>,
+}
+
+#[derive(Clone, Serialize)]
+struct TerminalOutput {
+    session: String,
+    data: String,
+}
+
+#[derive(Clone, Serialize)]
+struct TerminalExit {
+    session: String,
+    exit_code: Option<u32>,
+    /// Set when the shell was killed by a signal (Unix only).
+    signal: Option<String>,
+}
+
+impl TerminalSessions {
+    fn remove(&self, key: &(String, String), id: u64) -> Option<Session> {
+        let mut sessions = self.sessions.lock().ok()?;
+        if sessions.get(key)?.id != id {
+            return None;
+        }
+        sessions.remove(key)
+    }
+
+    /// Kills the shells of a window that has been closed.
+    pub fn close_window(&self, label: &str) {
+        if let Ok(mut sessions) = self.sessions.lock() {
+            sessions.retain(|(owner, _), session| {
+                if owner != label {
+                    return true;
+                }
+                let _ = session.killer.kill();
+                false
+            });
+        }
+    }
+
+    /// Kills every shell. Called on app exit.
+    pub fn close_all(&self) {
+        if let Ok(mut sessions) = self.sessions.lock() {
+            for (_, mut session) in sessions.drain() {
+                let _ = session.killer.kill();
+            }
+        }
+    }
+}
+
+fn pty_size(rows: u16, cols: u16) -> PtySize {
+    PtySize {
+        rows,
+        cols,
+        pixel_width: 0,
+        pixel_height: 0,
+    }
+}
+
+/// Starts the user's shell in a new session, in the project directory.
+/// Fails if the window already has a session called `session`.
+#[tauri::command]
+pub fn open_terminal(
+    app: AppHandle,
+    window: Window,
+    session: String,
+    rows: u16,
+    cols: u16,
+    state: State<'_, AppState>,
+    sessions: State<'_, TerminalSessions>,
+) -> Result<(), String> {
+    let key = (window.label().to_string(), session.clone());
+    let mut open = sessions.sessions.lock().map_err(|e| e.to_string())?;
+    if open.contains_key(&key) {
+        return Err(format!("Terminal session '{}' is already open", session));
+    }
+
+    let cwd = state.cwd.lock().map_err(|e| e.to_string())?.clone();
+    let pair = native_pty_system()
+        .openpty(pty_size(rows, cols))
+        .map_err(|e| format!("Failed to open a pseudo-terminal: {}", e))?;
+
+    let mut cmd = CommandBuilder::new_default_prog();
+    cmd.cwd(&cwd);
+    cmd.env("TERM", "xterm-256color");
+    let mut child = pair
+        .slave
+        .spawn_command(cmd)
+        .map_err(|e| format!("Failed to start shell: {}", e))?;
+    // The shell holds the only slave handle now, so reads see EOF once it
+    // exits
+    drop(pair.slave);
+
+    let mut reader = pair.master.try_clone_reader().map_err(|e| e.to_string())?;
+    let writer = pair.master.take_writer().map_err(|e| e.to_string())?;
+    let id = NEXT_SESSION_ID.fetch_add(1, Ordering::Relaxed);
+
+    open.insert(
+        key.clone(),
+        Session {
+            id,
+            master: pair.master,
+            writer: Arc::new(Mutex::new(writer)),
+            killer: child.clone_killer(),
+        },
+    );
+    drop(open);
+
+    // Reads block, so each session gets a thread of its own
+    std::thread::spawn(move || {
+        let (label, session) = &key;
+        let mut buf = [0u8; 4096];
+        let mut pending: Vec<u8> = Vec::new();
+
+        loop {
+            let n = match reader.read(&mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => n,
+            };
+            pending.extend_from_slice(&buf[..n]);
+
+            let valid = match std::str::from_utf8(&pending) {
+                Ok(_) => pending.len(),
+                Err(e) if e.error_len().is_none() => e.valid_up_to(),
+                Err(_) => pending.len(),
+            };
+            if valid == 0 {
+                continue;
+            }
+
+            let data = String::from_utf8_lossy(&pending[..valid]).to_string();
+            pending.drain(..valid);
+            let _ = app.emit_to(
+                label.as_str(),
+                "terminal-output",
+                TerminalOutput {
+                    session: session.clone(),
+                    data,
+                },
+            );
+        }
+
+        let status = child.wait().ok();
+        app.state::<TerminalSessions>().remove(&key, id);
+        let _ = app.emit_to(
+            label.as_str(),
+            "terminal-exit",
+            TerminalExit {
+                session: session.clone(),
+                exit_code: status.as_ref().map(|s| s.exit_code()),
+                signal: status.as_ref().and_then(|s| s.signal()).map(String::from),
+            },
+        );
+    });
+
+    Ok(())
+}
+
+/// Sends keystrokes (or pasted text) to a session's shell.
+#[tauri::command]
+pub fn write_terminal(
+    window: Window,
+    session: String,
+    data: String,
+    sessions: State<'_, TerminalSessions>,
+) -> Result<(), String> {
+    let writer = sessions
+        .sessions
+        .lock()
+        .map_err(|e| e.to_string())?
+        .get(&(window.label().to_string(), session.clone()))
+        .map(|entry| entry.writer.clone())
+        .ok_or_else(|| format!("No terminal session '{}'", session))?;
+
+    let mut writer = writer.lock().map_err(|e| e.to_string())?;
+    writer
+        .write_all(data.as_bytes())
+        .and_then(|_| writer.flush())
+        .map_err(|e| format!("Failed to write to terminal: {}", e))
+}
+
+#[tauri::command]
+pub fn resize_terminal(
+    window: Window,
+    session: String,
+    rows: u16,
+    cols: u16,
+    sessions: State<'_, TerminalSessions>,
+) -> Result<(), String> {
+    let sessions = sessions.sessions.lock().map_err(|e| e.to_string())?;
+    let entry = sessions
+        .get(&(window.label().to_string(), session.clone()))
+        .ok_or_else(|| format!("No terminal session '{}'", session))?;
+
+    entry
+        .master
+        .resize(pty_size(rows, cols))
+        .map_err(|e| format!("Failed to resize terminal: {}", e))
+}
+
+/// Kills a session's shell. `terminal-exit` follows once it is gone.
+#[tauri::command]
+pub fn close_terminal(
+    window: Window,
+    session: String,
+    sessions: State<'_, TerminalSessions>,
+) -> Result<(), String> {
+    let mut sessions = sessions.sessions.lock().map_err(|e| e.to_string())?;
+    let mut entry = sessions
+        .remove(&(window.label().to_string(), session.clone()))
+        .ok_or_else(|| format!("No terminal session '{}'", session))?;
+
+    entry
+        .killer
+        .kill()
+        .map_err(|e| format!("Failed to close terminal: {}", e))
+}
+
+/// Names of the window's open sessions.
+#[tauri::command]
+pub fn list_terminals(
+    window: Window,
+    sessions: State<'_, TerminalSessions>,
+) -> Result<Vec<String>, String> {
+    let sessions = sessions.sessions.lock().map_err(|e| e.to_string())?;
+    let mut names: Vec<String> = sessions
+        .keys()
+        .filter(|(label, _)| label == window.label())
+        .map(|(_, name)| name.clone())
+        .collect();
+    names.sort();
+    Ok(names)
+}
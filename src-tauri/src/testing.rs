@@ -0,0 +1,295 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, State};
+
+use crate::coverage::CoverageReport;
+use crate::execution::{
+    AppState, ExecutionResult, PreparedRun, RunRegistry, RunStatus, StopReason,
+};
+use crate::run_config::RunConfig;
+use crate::settings;
+
+const TESTS_FILE: &str = "tests.toml";
+
+/// Diffs bigger than this many line pairs only report the first mismatch.
+const MAX_DIFF_CELLS: usize = 1_000_000;
+
+/// One stdin/expected-stdout pair from `.pseudoide/tests.toml`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TestCase {
+    pub name: String,
+    #[serde(default)]
+    pub input: String,
+    pub expected: String,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct TestFile {
+    #[serde(default, rename = "case")]
+    cases: Vec<TestCase>,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Verdict {
+    Pass,
+    /// The program exited cleanly but printed the wrong output.
+    Fail,
+    Timeout,
+    /// Crashed, exited non-zero, hit a resource limit or failed to start.
+    RuntimeError,
+    /// Not run because the test run was stopped.
+    Skipped,
+}
+
+/// Emitted as `test-case-result` as each case finishes.
+#[derive(Serialize, Clone)]
+pub struct TestCaseResult {
+    pub run_id: u64,
+    pub name: String,
+    pub verdict: Verdict,
+    /// Line diff of the expected output against the actual one, with `-`
+    /// for expected lines that are missing and `+` for unexpected ones.
+    /// Empty unless the verdict is `fail`.
+    pub diff: String,
+    pub stdout: String,
+    pub stderr: String,
+    pub exit_code: Option<i32>,
+    pub reason: Option<StopReason>,
+    pub run_time_ms: Option<u64>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct TestReport {
+    pub run_id: u64,
+    /// The compile phase's result when the build failed, in which case no
+    /// case was run.
+    pub build_error: Option<ExecutionResult>,
+    pub cases: Vec<TestCaseResult>,
+    pub passed: usize,
+    pub failed: usize,
+    /// Cases not run because the test run was stopped.
+    pub skipped: usize,
+    /// What the cases covered between them, when asked for.
+    pub coverage: Option<CoverageReport>,
+}
+
+fn tests_path(project_dir: &Path) -> PathBuf {
+    project_dir.join(settings::SETTINGS_DIR).join(TESTS_FILE)
+}
+
+fn load(project_dir: &Path) -> Result<Vec<TestCase>, String> {
+    let path = tests_path(project_dir);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let content =
+        std::fs::read_to_string(&path).map_err(|e| format!("Failed to read test cases: {}", e))?;
+    let file: TestFile =
+        toml::from_str(&content).map_err(|e| format!("Invalid test cases in {:?}: {}", path, e))?;
+    Ok(file.cases)
+}
+
+#[tauri::command]
+pub fn list_test_cases(state: State<'_, AppState>) -> Result<Vec<TestCase>, String> {
+    let cwd = state.cwd.lock().map_err(|e| e.to_string())?.clone();
+    load(&cwd)
+}
+
+#[tauri::command]
+pub fn save_test_cases(cases: Vec<TestCase>, state: State<'_, AppState>) -> Result<(), String> {
+    let cwd = state.cwd.lock().map_err(|e| e.to_string())?.clone();
+    let path = tests_path(&cwd);
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+
+    let content = toml::to_string_pretty(&TestFile { cases }).map_err(|e| e.to_string())?;
+    std::fs::write(&path, content).map_err(|e| format!("Failed to write test cases: {}", e))
+}
+
+/// Builds the code once, as a single file even in a project, then runs the
+/// binary against every test case.
+/// Compiler output streams like `execute_code`'s; program output is only
+/// captured. `kill_execution` with the report's run id (also sent with each
+/// `test-case-result`) stops the run, skipping the remaining cases. With
+/// `coverage`, the report says which lines the cases ran.
+#[tauri::command]
+pub async fn run_tests(
+    app: AppHandle,
+    language: String,
+    code: String,
+    coverage: Option<bool>,
+    state: State<'_, AppState>,
+    registry: State<'_, RunRegistry>,
+) -> Result<TestReport, String> {
+    let cwd = state.cwd.lock().map_err(|e| e.to_string())?.clone();
+    let cases = load(&cwd)?;
+    if cases.is_empty() {
+        return Err(format!("No test cases in {:?}", tests_path(&cwd)));
+    }
+
+    // The cases are for the code in the editor, not a project around it
+    let config = RunConfig {
+        coverage: coverage.unwrap_or(false),
+        single_file: true,
+        ..Default::default()
+    };
+    let prepared = PreparedRun::new(&app, &cwd, &language, &code, config)?;
+    let run_id = prepared.run_id;
+    registry.insert(run_id, prepared.initial_status());
+
+    let mut report = TestReport {
+        run_id,
+        build_error: None,
+        cases: Vec::new(),
+        passed: 0,
+        failed: 0,
+        skipped: 0,
+        coverage: None,
+    };
+
+    match prepared.build(&app).await {
+        Ok(_) => {
+            registry.set_status(run_id, RunStatus::Running);
+            let mut stopped = false;
+            for case in &cases {
+                let result = if stopped {
+                    skipped(run_id, case)
+                } else {
+                    let outcome = prepared.run_with_input(&app, case.input.as_bytes()).await;
+                    // A timeout ends this case only; a kill ends the run
+                    stopped = outcome.reason == Some(StopReason::Killed);
+                    registry.clear_stop_reason(run_id);
+                    judge(case, outcome)
+                };
+
+                match result.verdict {
+                    Verdict::Pass => report.passed += 1,
+                    Verdict::Skipped => report.skipped += 1,
+                    _ => report.failed += 1,
+                }
+                let _ = app.emit("test-case-result", result.clone());
+                report.cases.push(result);
+            }
+            report.coverage = prepared.coverage().await;
+        }
+        Err(failed) => report.build_error = Some(failed),
+    }
+
+    registry.remove(run_id);
+    prepared.release(&app);
+    Ok(report)
+}
+
+fn judge(case: &TestCase, result: ExecutionResult) -> TestCaseResult {
+    let expected = normalized_lines(&case.expected);
+    let actual = normalized_lines(&result.stdout);
+
+    let verdict = match result.reason {
+        Some(StopReason::Timeout) => Verdict::Timeout,
+        Some(StopReason::Killed) => Verdict::Skipped,
+        Some(_) => Verdict::RuntimeError,
+        None if result.error.is_some() || !result.success => Verdict::RuntimeError,
+        None if expected != actual => Verdict::Fail,
+        None => Verdict::Pass,
+    };
+    let diff = if verdict == Verdict::Fail {
+        diff_lines(&expected, &actual)
+    } else {
+        String::new()
+    };
+
+    TestCaseResult {
+        run_id: result.run_id,
+        name: case.name.clone(),
+        verdict,
+        diff,
+        stdout: result.stdout,
+        stderr: result.stderr,
+        exit_code: result.exit_code,
+        reason: result.reason,
+        run_time_ms: result.run_time_ms,
+        error: result.error,
+    }
+}
+
+fn skipped(run_id: u64, case: &TestCase) -> TestCaseResult {
+    TestCaseResult {
+        run_id,
+        name: case.name.clone(),
+        verdict: Verdict::Skipped,
+        diff: String::new(),
+        stdout: String::new(),
+        stderr: String::new(),
+        exit_code: None,
+        reason: None,
+        run_time_ms: None,
+        error: None,
+    }
+}
+
+/// Output lines as judged: trailing whitespace on each line and trailing
+/// blank lines don't count.
+fn normalized_lines(output: &str) -> Vec<&str> {
+    let mut lines: Vec<&str> = output.lines().map(str::trim_end).collect();
+    while lines.last().is_some_and(|line| line.is_empty()) {
+        lines.pop();
+    }
+    lines
+}
+
+fn diff_lines(expected: &[&str], actual: &[&str]) -> String {
+    let (n, m) = (expected.len(), actual.len());
+    if n.saturating_mul(m) > MAX_DIFF_CELLS {
+        return first_mismatch(expected, actual);
+    }
+
+    // lcs[i][j]: length of the longest common subsequence of expected[i..]
+    // and actual[j..]
+    let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if expected[i] == actual[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut diff = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && expected[i] == actual[j] {
+            diff.push_str(&format!("  {}\n", expected[i]));
+            i += 1;
+            j += 1;
+        } else if i < n && (j == m || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            diff.push_str(&format!("- {}\n", expected[i]));
+            i += 1;
+        } else {
+            diff.push_str(&format!("+ {}\n", actual[j]));
+            j += 1;
+        }
+    }
+    diff
+}
+
+fn first_mismatch(expected: &[&str], actual: &[&str]) -> String {
+    let line = expected
+        .iter()
+        .zip(actual)
+        .position(|(e, a)| e != a)
+        .unwrap_or(expected.len().min(actual.len()));
+
+    format!(
+        "Outputs first differ at line {}:\n- {}\n+ {}\n",
+        line + 1,
+        expected.get(line).unwrap_or(&"<end of output>"),
+        actual.get(line).unwrap_or(&"<end of output>")
+    )
+}
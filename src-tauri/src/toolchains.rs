@@ -0,0 +1,497 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager, State};
+
+use crate::diagnostics::DiagnosticFormat;
+use crate::execution::AppState;
+use crate::settings;
+
+const TOOLCHAINS_FILE: &str = "toolchains.toml";
+
+/// Languages available out of the box. `toolchains.toml` in the app data
+/// directory, then in the project's `.pseudoide` directory, can add entries
+/// or replace these by name.
+///
+/// Command templates are argument lists; `{source}`, `{binary}` and `{dir}`
+/// are replaced with the source file and the compiled output (both in the
+/// run's build directory) and the directory the program runs in. Programs
+/// are looked up on PATH before a run starts; `run_fallbacks` are tried in
+/// order when the `run` program isn't installed. `profiles` holds the flags
+/// appended to `compile` for each named build profile, and `emit` the flags
+/// that make it write assembly or LLVM IR to `{binary}` instead.
+const BUILTIN_TOOLCHAINS: &str = r#"
+[[toolchain]]
+name = "python"
+extension = "py"
+run = ["python", "{source}"]
+run_fallbacks = [["python3", "{source}"]]
+install_hint = "Install Python 3 from https://www.python.org/downloads/"
+
+[[toolchain]]
+name = "javascript"
+aliases = ["js"]
+extension = "js"
+run = ["node", "{source}"]
+install_hint = "Install Node.js from https://nodejs.org/"
+
+[[toolchain]]
+name = "typescript"
+aliases = ["ts"]
+extension = "ts"
+compile = ["tsc", "--noEmit", "--pretty", "false", "--strict", "--target", "es2022", "--module", "nodenext", "{source}"]
+run = ["tsx", "{source}"]
+run_fallbacks = [
+    ["bun", "run", "{source}"],
+    ["deno", "run", "--allow-all", "{source}"],
+    ["ts-node", "{source}"],
+    ["node", "--experimental-strip-types", "{source}"],
+]
+diagnostics = "tsc"
+install_hint = "TypeScript needs tsc to type-check and a runtime to run: `npm install -g typescript tsx`"
+
+[[toolchain]]
+name = "c++"
+aliases = ["cpp"]
+extension = "cpp"
+compile = ["g++", "{source}", "-o", "{binary}"]
+run = ["{binary}"]
+diagnostics = "gcc"
+install_hint = "Install g++ (GCC) or add it to PATH"
+
+[toolchain.profiles]
+debug = ["-g", "-O0", "-Wall"]
+release = ["-O2", "-Wall"]
+sanitize = ["-g", "-O1", "-fno-omit-frame-pointer", "-fsanitize=address,undefined", "-Wall"]
+
+[toolchain.emit]
+asm = ["-S", "-g", "-fno-asynchronous-unwind-tables"]
+
+[[toolchain]]
+name = "c"
+extension = "c"
+compile = ["gcc", "{source}", "-o", "{binary}"]
+run = ["{binary}"]
+diagnostics = "gcc"
+install_hint = "Install gcc or add it to PATH"
+
+[toolchain.profiles]
+debug = ["-g", "-O0", "-Wall"]
+release = ["-O2", "-Wall"]
+sanitize = ["-g", "-O1", "-fno-omit-frame-pointer", "-fsanitize=address,undefined", "-Wall"]
+
+[toolchain.emit]
+asm = ["-S", "-g", "-fno-asynchronous-unwind-tables"]
+
+[[toolchain]]
+name = "rust"
+extension = "rs"
+compile = ["rustc", "--error-format=json", "{source}", "-o", "{binary}"]
+run = ["{binary}"]
+diagnostics = "rustc"
+install_hint = "Install Rust from https://rustup.rs/"
+
+[toolchain.profiles]
+debug = ["-g", "-C", "opt-level=0"]
+release = ["-C", "opt-level=3"]
+sanitize = ["-g", "-C", "overflow-checks=on", "-C", "debug-assertions=on"]
+
+[toolchain.emit]
+asm = ["--emit", "asm", "-C", "debuginfo=1"]
+llvm_ir = ["--emit", "llvm-ir", "-C", "debuginfo=1"]
+
+[[toolchain]]
+name = "go"
+aliases = ["golang"]
+extension = "go"
+compile = ["go", "build", "-o", "{binary}", "{source}"]
+run = ["{binary}"]
+version = ["go", "version"]
+diagnostics = "go"
+install_hint = "Install Go from https://go.dev/dl/"
+"#;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Toolchain {
+    pub name: String,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// Extension of the source file the code is written to, without the dot.
+    pub extension: String,
+    /// Build step for compiled languages; interpreted ones leave it out.
+    #[serde(default)]
+    pub compile: Option<Vec<String>>,
+    pub run: Vec<String>,
+    /// Alternatives to `run`, used in order when its program isn't on PATH.
+    #[serde(default)]
+    pub run_fallbacks: Vec<Vec<String>>,
+    /// Compile flags per build profile. A toolchain without flags for a
+    /// profile can't build with it.
+    #[serde(default)]
+    pub profiles: ProfileFlags,
+    /// Compile flags that write the compiler's output instead of a binary.
+    #[serde(default)]
+    pub emit: EmitFlags,
+    /// Prints the compiler's version, which is part of the compile cache
+    /// key. Defaults to the compile program with `--version`.
+    #[serde(default)]
+    pub version: Option<Vec<String>>,
+    /// How to parse the compiler's output into diagnostics, if at all.
+    #[serde(default)]
+    pub diagnostics: Option<DiagnosticFormat>,
+    /// Shown when a program the toolchain needs is not installed.
+    #[serde(default)]
+    pub install_hint: Option<String>,
+}
+
+/// Named sets of compile flags for single-file builds. The standard profile
+/// is the compile command as it is.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BuildProfile {
+    #[default]
+    Standard,
+    /// Debug info, no optimisation and warnings; also what the debugger
+    /// builds with.
+    Debug,
+    Release,
+    /// Runtime checks: ASan and UBSan for C and C++, overflow checks for
+    /// Rust.
+    Sanitize,
+}
+
+impl BuildProfile {
+    pub fn name(&self) -> &'static str {
+        match self {
+            BuildProfile::Standard => "standard",
+            BuildProfile::Debug => "debug",
+            BuildProfile::Release => "release",
+            BuildProfile::Sanitize => "sanitize",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ProfileFlags {
+    #[serde(default)]
+    pub debug: Vec<String>,
+    #[serde(default)]
+    pub release: Vec<String>,
+    #[serde(default)]
+    pub sanitize: Vec<String>,
+}
+
+/// What the compiler can be asked to write instead of a binary.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EmitKind {
+    Asm,
+    LlvmIr,
+}
+
+impl EmitKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            EmitKind::Asm => "assembly",
+            EmitKind::LlvmIr => "LLVM IR",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct EmitFlags {
+    #[serde(default)]
+    pub asm: Vec<String>,
+    #[serde(default)]
+    pub llvm_ir: Vec<String>,
+}
+
+/// Paths substituted into a toolchain's command templates.
+pub struct BuildPaths<'a> {
+    pub source: &'a Path,
+    pub binary: &'a Path,
+    pub dir: &'a Path,
+}
+
+impl Toolchain {
+    /// The flags `profile` appends to the compile command, or `None` when
+    /// the toolchain doesn't support it.
+    pub fn profile_flags(&self, profile: BuildProfile) -> Option<&[String]> {
+        let flags = match profile {
+            BuildProfile::Standard => return Some(&[]),
+            BuildProfile::Debug => &self.profiles.debug,
+            BuildProfile::Release => &self.profiles.release,
+            BuildProfile::Sanitize => &self.profiles.sanitize,
+        };
+        (self.compile.is_some() && !flags.is_empty()).then_some(&flags[..])
+    }
+
+    /// The profile to build with and its flags. A profile asked for by name
+    /// must exist; the `default` one just doesn't apply to toolchains
+    /// without it.
+    pub fn select_profile(
+        &self,
+        requested: Option<BuildProfile>,
+        default: BuildProfile,
+    ) -> Result<(BuildProfile, &[String]), String> {
+        match requested {
+            Some(profile) => match self.profile_flags(profile) {
+                Some(flags) => Ok((profile, flags)),
+                None => Err(format!(
+                    "The {} build profile is not available for {}",
+                    profile.name(),
+                    self.name
+                )),
+            },
+            None => Ok(match self.profile_flags(default) {
+                Some(flags) => (default, flags),
+                None => (BuildProfile::Standard, &[][..]),
+            }),
+        }
+    }
+
+    /// The flags that make the compile command write `kind`, or `None`
+    /// when the toolchain can't.
+    pub fn emit_flags(&self, kind: EmitKind) -> Option<&[String]> {
+        let flags = match kind {
+            EmitKind::Asm => &self.emit.asm,
+            EmitKind::LlvmIr => &self.emit.llvm_ir,
+        };
+        (self.compile.is_some() && !flags.is_empty()).then_some(&flags[..])
+    }
+
+    /// The expanded compile command, with its program resolved on PATH.
+    pub fn compile_argv(&self, paths: &BuildPaths) -> Result<Option<Vec<String>>, String> {
+        let Some(template) = &self.compile else {
+            return Ok(None);
+        };
+        let mut argv = expand(template, paths);
+        let program = resolve(&argv[0]).ok_or_else(|| self.missing(&[&argv[0]]))?;
+        argv[0] = program;
+        Ok(Some(argv))
+    }
+
+    /// The expanded run command: `run`, or the first of `run_fallbacks`
+    /// whose program is installed.
+    pub fn run_argv(&self, paths: &BuildPaths) -> Result<Vec<String>, String> {
+        let candidates: Vec<&Vec<String>> = std::iter::once(&self.run)
+            .chain(&self.run_fallbacks)
+            .collect();
+        let programs: Vec<&str> = candidates.iter().map(|c| c[0].as_str()).collect();
+
+        for template in &candidates {
+            let mut argv = expand(template, paths);
+            if let Some(program) = resolve(&argv[0]) {
+                argv[0] = program;
+                return Ok(argv);
+            }
+        }
+
+        Err(self.missing(&programs))
+    }
+
+    /// The program of `run`, or of the first of `run_fallbacks` that is
+    /// installed, resolved on PATH. For interpreted languages, the
+    /// interpreter.
+    pub fn run_program(&self) -> Result<String, String> {
+        let programs: Vec<&str> = std::iter::once(&self.run)
+            .chain(&self.run_fallbacks)
+            .map(|c| c[0].as_str())
+            .collect();
+        programs
+            .iter()
+            .find_map(|program| resolve(program))
+            .ok_or_else(|| self.missing(&programs))
+    }
+
+    /// The command printing the compiler's version, with its program
+    /// resolved on PATH. `None` for interpreted languages.
+    pub fn version_argv(&self) -> Option<Vec<String>> {
+        let mut argv = match &self.version {
+            Some(version) => version.clone(),
+            None => vec![self.compile.as_ref()?[0].clone(), "--version".to_string()],
+        };
+        argv[0] = resolve(&argv[0])?;
+        Some(argv)
+    }
+
+    fn missing(&self, programs: &[&str]) -> String {
+        let mut message = if programs.len() == 1 {
+            format!(
+                "Cannot run {}: `{}` was not found on PATH.",
+                self.name, programs[0]
+            )
+        } else {
+            format!(
+                "Cannot run {}: none of {} was found on PATH.",
+                self.name,
+                programs
+                    .iter()
+                    .map(|p| format!("`{}`", p))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        };
+        if let Some(hint) = &self.install_hint {
+            message.push(' ');
+            message.push_str(hint);
+        }
+        message
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.extension.is_empty() {
+            return Err(format!("Toolchain '{}' has no extension", self.name));
+        }
+        if self.run.is_empty() {
+            return Err(format!(
+                "Toolchain '{}' has an empty run command",
+                self.name
+            ));
+        }
+        if self.run_fallbacks.iter().any(|r| r.is_empty()) {
+            return Err(format!(
+                "Toolchain '{}' has an empty run fallback",
+                self.name
+            ));
+        }
+        if self.compile.as_ref().is_some_and(|c| c.is_empty()) {
+            return Err(format!(
+                "Toolchain '{}' has an empty compile command",
+                self.name
+            ));
+        }
+        if self.version.as_ref().is_some_and(|v| v.is_empty()) {
+            return Err(format!(
+                "Toolchain '{}' has an empty version command",
+                self.name
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn expand(template: &[String], paths: &BuildPaths) -> Vec<String> {
+    template
+        .iter()
+        .map(|arg| {
+            arg.replace("{source}", &paths.source.to_string_lossy())
+                .replace("{binary}", &paths.binary.to_string_lossy())
+                .replace("{dir}", &paths.dir.to_string_lossy())
+        })
+        .collect()
+}
+
+/// Resolves the program of a command on PATH. Paths are taken as they are:
+/// a compiled binary doesn't exist until the build has run.
+fn resolve(program: &str) -> Option<String> {
+    if Path::new(program).components().count() > 1 {
+        return Some(program.to_string());
+    }
+    find_program(program).map(|path| path.to_string_lossy().into_owned())
+}
+
+/// Full path of `program` if it is on PATH. On Windows the PATHEXT
+/// extensions are tried too, so `tsc` finds `tsc.cmd`.
+pub fn find_program(program: &str) -> Option<PathBuf> {
+    let extensions: Vec<String> = if cfg!(target_os = "windows") {
+        std::iter::once(String::new())
+            .chain(
+                std::env::var("PATHEXT")
+                    .unwrap_or_else(|_| ".COM;.EXE;.BAT;.CMD".to_string())
+                    .split(';')
+                    .map(|ext| ext.to_string()),
+            )
+            .collect()
+    } else {
+        vec![String::new()]
+    };
+
+    std::env::split_paths(&std::env::var_os("PATH")?).find_map(|dir| {
+        extensions
+            .iter()
+            .map(|ext| dir.join(format!("{}{}", program, ext)))
+            .find(|candidate| candidate.is_file())
+    })
+}
+
+#[derive(Deserialize, Default)]
+struct ToolchainFile {
+    #[serde(default, rename = "toolchain")]
+    toolchains: Vec<Toolchain>,
+}
+
+pub struct ToolchainRegistry {
+    toolchains: Vec<Toolchain>,
+}
+
+impl ToolchainRegistry {
+    fn builtin() -> Self {
+        let file: ToolchainFile =
+            toml::from_str(BUILTIN_TOOLCHAINS).expect("built-in toolchains are valid TOML");
+        Self {
+            toolchains: file.toolchains,
+        }
+    }
+
+    /// Adds the toolchains in `path`, replacing existing ones with the same
+    /// name. A missing file is not an error.
+    fn merge_file(&mut self, path: &Path) -> Result<(), String> {
+        if !path.exists() {
+            return Ok(());
+        }
+
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+        let file: ToolchainFile = toml::from_str(&content)
+            .map_err(|e| format!("Invalid toolchains in {:?}: {}", path, e))?;
+
+        for toolchain in file.toolchains {
+            toolchain
+                .validate()
+                .map_err(|e| format!("{} (in {:?})", e, path))?;
+            self.toolchains
+                .retain(|t| !t.name.eq_ignore_ascii_case(&toolchain.name));
+            self.toolchains.push(toolchain);
+        }
+        Ok(())
+    }
+
+    /// Looks a language up by name, then by alias. Entries loaded later win,
+    /// so a project can claim an alias that a built-in toolchain also uses.
+    pub fn find(&self, language: &str) -> Option<&Toolchain> {
+        self.toolchains
+            .iter()
+            .rev()
+            .find(|t| t.name.eq_ignore_ascii_case(language))
+            .or_else(|| {
+                self.toolchains.iter().rev().find(|t| {
+                    t.aliases
+                        .iter()
+                        .any(|alias| alias.eq_ignore_ascii_case(language))
+                })
+            })
+    }
+}
+
+pub fn load(app: &AppHandle, project_dir: &Path) -> Result<ToolchainRegistry, String> {
+    let mut registry = ToolchainRegistry::builtin();
+
+    let app_data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    registry.merge_file(&app_data_dir.join(TOOLCHAINS_FILE))?;
+    registry.merge_file(
+        &project_dir
+            .join(settings::SETTINGS_DIR)
+            .join(TOOLCHAINS_FILE),
+    )?;
+
+    Ok(registry)
+}
+
+#[tauri::command]
+pub fn list_toolchains(
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<Vec<Toolchain>, String> {
+    let cwd = state.cwd.lock().map_err(|e| e.to_string())?.clone();
+    Ok(load(&app, &cwd)?.toolchains)
+}
@@ -0,0 +1,93 @@
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use crate::process;
+
+static NEXT_SCRATCH_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Scratch directories for builds, one per run, under the app cache dir.
+/// Each instance of the app has a directory of its own there, named after
+/// its pid, so instances never remove each other's builds.
+///
+/// Generated source and compiled binaries live in the run's directory so
+/// nothing is written into the user's project. The program itself still
+/// runs with the project directory as its cwd, so relative paths in the
+/// code resolve against the project as the user expects.
+pub struct BuildWorkspaces {
+    root: PathBuf,
+    /// Finished runs whose directories are kept around for inspection,
+    /// oldest first.
+    retained: Mutex<VecDeque<PathBuf>>,
+}
+
+impl BuildWorkspaces {
+    /// Anything left in `builds` by instances that are no longer running
+    /// is removed.
+    pub fn new(builds: PathBuf) -> Self {
+        remove_stale(&builds);
+        let root = builds.join(std::process::id().to_string());
+        let _ = std::fs::remove_dir_all(&root);
+        Self {
+            root,
+            retained: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub fn create(&self, run_id: u64) -> Result<PathBuf, String> {
+        self.create_dir(&format!("run-{}", run_id))
+    }
+
+    /// A directory for work on the code that isn't a run, such as linting.
+    /// The caller removes it when done; leftovers go with `cleanup`.
+    pub fn create_scratch(&self, kind: &str) -> Result<PathBuf, String> {
+        let id = NEXT_SCRATCH_ID.fetch_add(1, Ordering::Relaxed);
+        self.create_dir(&format!("{}-{}", kind, id))
+    }
+
+    fn create_dir(&self, name: &str) -> Result<PathBuf, String> {
+        let dir = self.root.join(name);
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create build directory: {}", e))?;
+        Ok(dir)
+    }
+
+    /// Hands back a finished run's directory. The `retain` most recent ones
+    /// are kept until the app exits; older ones are deleted.
+    pub fn release(&self, dir: PathBuf, retain: usize) {
+        let Ok(mut retained) = self.retained.lock() else {
+            let _ = std::fs::remove_dir_all(&dir);
+            return;
+        };
+
+        retained.push_back(dir);
+        while retained.len() > retain {
+            if let Some(old) = retained.pop_front() {
+                let _ = std::fs::remove_dir_all(old);
+            }
+        }
+    }
+
+    /// Removes every build directory of this instance. Called on app exit.
+    pub fn cleanup(&self) {
+        if let Ok(mut retained) = self.retained.lock() {
+            retained.clear();
+        }
+        let _ = std::fs::remove_dir_all(&self.root);
+    }
+}
+
+/// Removes the directories in `builds` whose instance has exited, along with
+/// anything else that isn't an instance's directory.
+fn remove_stale(builds: &Path) {
+    for entry in std::fs::read_dir(builds).into_iter().flatten().flatten() {
+        let owner = entry
+            .file_name()
+            .to_str()
+            .and_then(|name| name.parse::<u32>().ok());
+        if !owner.is_some_and(process::is_running) {
+            let _ = std::fs::remove_dir_all(entry.path());
+        }
+    }
+}
@@ -4,12 +4,74 @@ import ChatBox from "./components/ChatBox";
 import SetupModal from "./components/SetupModal";
 import FileSidebar from "./components/FileSidebar";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import { open } from '@tauri-apps/plugin-dialog';
-import { Menu, FolderOpen, FolderPlus, ArrowRightLeft, Play } from 'lucide-react';
+import { Menu, FolderOpen, FolderPlus, ArrowRightLeft, Play, Square } from 'lucide-react';
 import ProjectInitModal, { ProjectData } from './components/ProjectInitModal';
 
 import TerminalPanel from "./components/TerminalPanel";
 
+type StopReason = 'timeout' | 'killed' | 'cpu_limit' | 'memory_limit' | 'file_size_limit';
+
+interface Diagnostic {
+  severity: 'error' | 'warning' | 'note' | 'help';
+  file: string | null;
+  line: number | null;
+  column: number | null;
+  message: string;
+  notes: string[];
+  code: string | null;
+  source: string | null;
+}
+
+interface ExecutionResult {
+  run_id: number;
+  phase: 'compile' | 'run';
+  success: boolean;
+  exit_code: number | null;
+  signal: number | null;
+  stdout: string;
+  stderr: string;
+  compile_time_ms: number | null;
+  run_time_ms: number | null;
+  peak_memory_kb: number | null;
+  reason: StopReason | null;
+  error: string | null;
+  diagnostics: Diagnostic[];
+  project: 'cargo' | 'go_module' | 'npm' | 'python' | 'cmake' | 'make' | null;
+  note: string | null;
+  cached_build: boolean;
+  sanitizer_findings: SanitizerFinding[];
+  coverage: CoverageReport | null;
+}
+
+interface SanitizerFinding {
+  sanitizer: string;
+  kind: string;
+  message: string;
+  line: number | null;
+  column: number | null;
+  stack: { function: string | null; file: string | null; line: number | null; column: number | null }[];
+}
+
+interface CoverageReport {
+  tool: string;
+  lines: { line: number; hits: number }[];
+  branches: { line: number; block: number; branch: number; hits: number | null }[];
+  lines_found: number;
+  lines_hit: number;
+  lcov: string;
+  error: string | null;
+}
+
+const STOP_MESSAGES: Record<StopReason, string> = {
+  timeout: 'Timed out.',
+  killed: 'Stopped.',
+  cpu_limit: 'Killed: CPU time limit exceeded.',
+  memory_limit: 'Killed: memory limit exceeded.',
+  file_size_limit: 'Killed: file size limit exceeded.',
+};
+
 function App() {
   const [isSetupOpen, setIsSetupOpen] = useState(false);
   const [pseudocode, setPseudocode] = useState<string>('// Write your pseudocode here...\n\nFUNCTION calculate_fibonacci(n):\n  IF n <= 1 RETURN n\n  RETURN calculate_fibonacci(n-1) + calculate_fibonacci(n-2)');
@@ -20,11 +82,13 @@ function App() {
   const [isSidebarOpen, setIsSidebarOpen] = useState(false);
   const [sidebarRefreshKey, setSidebarRefreshKey] = useState(0);
   const [projectName, setProjectName] = useState("Testing Grounds");
+  const [projectPath, setProjectPath] = useState("");
 
   // Terminal State
   const [isTerminalOpen, setIsTerminalOpen] = useState(true);
   const [terminalOutput, setTerminalOutput] = useState('');
   const [isRunningCode, setIsRunningCode] = useState(false);
+  const [activeRunId, setActiveRunId] = useState<number | null>(null);
   const [terminalHeight, setTerminalHeight] = useState(200);
   const [isTerminalCollapsed, setIsTerminalCollapsed] = useState(true);
 
@@ -33,9 +97,59 @@ function App() {
     initTestingGrounds();
   }, []);
 
+  // Program output is streamed from the backend while it runs
+  useEffect(() => {
+    const append = (event: { payload: { data: string } }) => {
+      setTerminalOutput(prev => prev + event.payload.data);
+    };
+    const unlistenStdout = listen<{ run_id: number; data: string }>('execution-stdout', append);
+    const unlistenStderr = listen<{ run_id: number; data: string }>('execution-stderr', append);
+    const unlistenExit = listen<ExecutionResult>('execution-exit', (event) => {
+      const { phase, success, exit_code, signal, error, reason, run_time_ms, sanitizer_findings, coverage, note } = event.payload;
+      if (note) {
+        setTerminalOutput(prev => prev + `\n> ${note}`);
+      }
+      if (error) {
+        setTerminalOutput(prev => prev + `\n> Error: ${error}\n`);
+      } else if (reason) {
+        setTerminalOutput(prev => prev + `\n> ${STOP_MESSAGES[reason]}\n`);
+      } else if (phase === 'compile') {
+        setTerminalOutput(prev => prev + '\n> Compilation failed.\n');
+      } else if (success) {
+        setTerminalOutput(prev => prev + `\n> Done in ${run_time_ms ?? 0} ms.\n`);
+      } else if (signal !== null) {
+        setTerminalOutput(prev => prev + `\n> Terminated by signal ${signal}\n`);
+      } else {
+        setTerminalOutput(prev => prev + `\n> Exited with code ${exit_code ?? 'unknown'}\n`);
+      }
+      for (const finding of sanitizer_findings) {
+        const where = finding.line !== null ? ` (line ${finding.line})` : '';
+        setTerminalOutput(prev => prev + `> ${finding.sanitizer}: ${finding.kind}${where}: ${finding.message}\n`);
+      }
+      if (coverage?.error) {
+        setTerminalOutput(prev => prev + `> Coverage: ${coverage.error}\n`);
+      } else if (coverage) {
+        const unhit = coverage.lines.filter(l => l.hits === 0).map(l => l.line);
+        const missed = unhit.length > 0 ? `; never ran: ${unhit.join(', ')}` : '';
+        setTerminalOutput(prev => prev + `> Coverage (${coverage.tool}): ${coverage.lines_hit}/${coverage.lines_found} lines${missed}\n`);
+      }
+      setIsRunningCode(false);
+      setActiveRunId(current => (current === event.payload.run_id ? null : current));
+      // Refresh sidebar to show new files
+      setSidebarRefreshKey(prev => prev + 1);
+    });
+
+    return () => {
+      unlistenStdout.then(f => f());
+      unlistenStderr.then(f => f());
+      unlistenExit.then(f => f());
+    };
+  }, []);
+
   const initTestingGrounds = async () => {
     try {
       const path = await invoke<string>('ensure_testing_grounds');
+      setProjectPath(path);
       setTerminalOutput(prev => prev + `> Initialized Testing Grounds at: ${path}\n`);
       setSidebarRefreshKey(prev => prev + 1);
     } catch (e) {
@@ -58,6 +172,7 @@ function App() {
         await invoke('change_working_directory', { path: selected });
         // Update UI
         setProjectName(selected.split(/[\\/]/).pop() || "Project");
+        setProjectPath(selected);
         setSidebarRefreshKey(prev => prev + 1);
         setTerminalOutput(prev => prev + `> Switched to project: ${selected}\n`);
         // Auto-open sidebar on project open? Optional.
@@ -102,6 +217,7 @@ function App() {
 
       // Update UI
       setProjectName(data.name);
+      setProjectPath(fullPath);
       setSidebarRefreshKey(prev => prev + 1);
       setTerminalOutput(prev => prev + `> Switched to project: ${data.name}\n`);
       setIsSidebarOpen(true);
@@ -144,20 +260,35 @@ function App() {
       // Save Pseudocode first
       await invoke('write_file', { path: "main.pseudo", content: pseudocode });
 
-      // Execute (Backend will save generated code to main.[ext] and run it)
-      const output = await invoke<string>('execute_code', { language: targetLanguage, code: generatedCode });
-      setTerminalOutput(prev => prev + output + '\n> Done.\n');
-
-      // Refresh sidebar to show new files
-      setSidebarRefreshKey(prev => prev + 1);
+      // Execute (Backend builds the generated code in its own directory and streams its output)
+      const runId = await invoke<number>('execute_code', { language: targetLanguage, code: generatedCode });
+      setActiveRunId(runId);
     } catch (e) {
       setTerminalOutput(prev => prev + `> Error: ${e}\n`);
-    } finally {
       setIsRunningCode(false);
     }
   };
 
+  const handleStopCode = async () => {
+    if (activeRunId === null) return;
+    try {
+      await invoke('kill_execution', { runId: activeRunId });
+    } catch (e) {
+      setTerminalOutput(prev => prev + `> Error: ${e}\n`);
+    }
+  };
+
   const handleTerminalCommand = async (cmd: string) => {
+    // While a program is running, terminal input goes to its stdin
+    if (activeRunId !== null) {
+      try {
+        await invoke('write_stdin', { runId: activeRunId, line: cmd });
+      } catch (e) {
+        setTerminalOutput(prev => prev + `Error: ${e}\n`);
+      }
+      return;
+    }
+
     try {
       const output = await invoke<string>('run_terminal_command', { command: cmd });
       setTerminalOutput(prev => prev + output + '\n');
@@ -166,6 +297,19 @@ function App() {
     }
   };
 
+  const handleTerminalInterrupt = async () => {
+    if (activeRunId !== null) {
+      await handleStopCode();
+      return;
+    }
+
+    try {
+      await invoke<boolean>('interrupt_terminal_command');
+    } catch (e) {
+      setTerminalOutput(prev => prev + `Error: ${e}\n`);
+    }
+  };
+
   const handleFileSelect = async (path: string, name: string) => {
     try {
       const content = await invoke<string>('read_file', { path });
@@ -276,6 +420,16 @@ function App() {
                 )}
                 <span>Run</span>
               </button>
+
+              {activeRunId !== null && (
+                <button
+                  onClick={handleStopCode}
+                  className="flex items-center gap-2 px-3 py-1.5 rounded text-zinc-400 hover:text-white hover:bg-zinc-800 transition-colors text-sm font-medium"
+                >
+                  <Square size={14} fill="currentColor" />
+                  <span>Stop</span>
+                </button>
+              )}
             </div>
           </div>
 
@@ -311,6 +465,8 @@ function App() {
           isCollapsed={isTerminalCollapsed}
           setIsCollapsed={setIsTerminalCollapsed}
           onCommand={handleTerminalCommand}
+          onInterrupt={handleTerminalInterrupt}
+          projectPath={projectPath}
         />
       </div>
     </div>
@@ -1,5 +1,11 @@
 import React, { useEffect, useRef, useState } from 'react';
 import { X, Terminal as TerminalIcon, ChevronUp, ChevronDown } from 'lucide-react';
+import { invoke } from '@tauri-apps/api/core';
+
+interface Completions {
+    word: string;
+    candidates: { value: string; kind: 'file' | 'directory' | 'executable' | 'builtin' | 'git_subcommand' }[];
+}
 
 interface TerminalPanelProps {
     isOpen: boolean;
@@ -12,6 +18,8 @@ interface TerminalPanelProps {
     isCollapsed: boolean;
     setIsCollapsed: (c: boolean) => void;
     onCommand: (cmd: string) => Promise<void>;
+    onInterrupt: () => Promise<void>;
+    projectPath: string;
 }
 
 const TerminalPanel: React.FC<TerminalPanelProps> = ({
@@ -24,7 +32,9 @@ const TerminalPanel: React.FC<TerminalPanelProps> = ({
     setHeight,
     isCollapsed,
     setIsCollapsed,
-    onCommand
+    onCommand,
+    onInterrupt,
+    projectPath
 }) => {
     const bottomRef = useRef<HTMLDivElement>(null);
     const [isResizing, setIsResizing] = useState(false);
@@ -32,6 +42,14 @@ const TerminalPanel: React.FC<TerminalPanelProps> = ({
     const [commandHistory, setCommandHistory] = useState<string[]>([]);
     const [historyIndex, setHistoryIndex] = useState(-1);
 
+    // History is kept per project by the backend
+    useEffect(() => {
+        setHistoryIndex(-1);
+        invoke<string[]>('get_terminal_history')
+            .then(setCommandHistory)
+            .catch(() => {});
+    }, [projectPath]);
+
     useEffect(() => {
         if (bottomRef.current) {
             bottomRef.current.scrollIntoView({ behavior: 'smooth' });
@@ -70,19 +88,63 @@ const TerminalPanel: React.FC<TerminalPanelProps> = ({
         setIsResizing(true);
     };
 
+    const complete = async () => {
+        let result: Completions;
+        try {
+            result = await invoke<Completions>('complete_terminal_input', { input: inputValue });
+        } catch {
+            return;
+        }
+        const { word, candidates } = result;
+        if (candidates.length === 0) return;
+
+        const base = inputValue.slice(0, inputValue.length - word.length);
+        if (candidates.length === 1) {
+            const [only] = candidates;
+            const done = only.kind !== 'directory' && !/^['"]/.test(only.value);
+            setInputValue(base + only.value + (done ? ' ' : ''));
+            return;
+        }
+
+        // Several matches: extend to what they share, or list them
+        const values = candidates.map(c => c.value);
+        let common = values[0];
+        for (const value of values) {
+            while (!value.startsWith(common)) common = common.slice(0, -1);
+        }
+        if (common.length > word.length) {
+            setInputValue(base + common);
+        } else {
+            setOutput(output + `\n$ ${inputValue}\n${values.join('  ')}\n`);
+        }
+    };
+
     const handleKeyDown = async (e: React.KeyboardEvent<HTMLInputElement>) => {
-        if (e.key === 'Enter') {
+        // Ctrl+C interrupts, unless there is a selection to copy
+        if (e.ctrlKey && e.key === 'c' && e.currentTarget.selectionStart === e.currentTarget.selectionEnd) {
+            e.preventDefault();
+            setInputValue('');
+            await onInterrupt();
+        } else if (e.key === 'Enter') {
             const cmd = inputValue.trim();
             if (!cmd) return;
 
             setCommandHistory(prev => [...prev, cmd]);
             setHistoryIndex(-1);
             setInputValue('');
+            // Input for a running program isn't a command
+            if (!isRunning) {
+                invoke('add_terminal_history', { command: cmd }).catch(() => {});
+            }
 
             // Echo command
             setOutput(output + `\n$ ${cmd}\n`);
 
             await onCommand(cmd);
+        } else if (e.key === 'Tab') {
+            e.preventDefault();
+            if (isRunning) return;
+            await complete();
         } else if (e.key === 'ArrowUp') {
             e.preventDefault();
             if (commandHistory.length > 0) {